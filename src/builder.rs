@@ -0,0 +1,115 @@
+use crate::{
+    expr::{
+        builtin::BuiltinSet,
+        context::{DivisionMode, OutputMode, OverflowPolicy, Settings},
+    },
+    lex::Dialect,
+    Error, Evaluator,
+};
+
+/// Builder of an [`Evaluator`] with a selection of built-in sets and settings
+#[derive(Debug, Clone, Default)]
+pub struct EvaluatorBuilder {
+    sets: Vec<BuiltinSet>,
+    settings: Settings,
+    /// First invalid setting, returned by [`EvaluatorBuilder::build`]
+    error: Option<Error>,
+}
+
+impl EvaluatorBuilder {
+    /// Start without any built-in set
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Start with every built-in set enabled by the cargo features
+    pub fn standard() -> Self {
+        Self {
+            sets: BuiltinSet::standard(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_set(mut self, set: BuiltinSet) -> Self {
+        if !self.sets.contains(&set) {
+            self.sets.push(set);
+        }
+        self
+    }
+
+    pub fn without_set(mut self, set: BuiltinSet) -> Self {
+        self.sets.retain(|s| *s != set);
+        self
+    }
+
+    /// Bit width of operators without bit width hint, like `~`, `^` and rotates.
+    /// [`EvaluatorBuilder::build`] fails if the width is not in the range of 1 to 128
+    pub fn default_width(mut self, width: usize) -> Self {
+        let result = self.settings.set_default_width(width);
+        self.check(result)
+    }
+
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.settings.set_overflow(overflow);
+        self
+    }
+
+    pub fn division(mut self, division: DivisionMode) -> Self {
+        self.settings.set_division(division);
+        self
+    }
+
+    /// Radix of numbers without prefix.
+    /// [`EvaluatorBuilder::build`] fails if the radix is not in the range of 2 to 36
+    pub fn ibase(mut self, ibase: u32) -> Self {
+        let result = self.settings.set_ibase(ibase);
+        self.check(result)
+    }
+
+    /// Give hexadecimal and binary literals the width of their digits, leading zeros included.
+    /// Operators without bit width hint then use the widest of their operands
+    pub fn infer_width(mut self, infer_width: bool) -> Self {
+        self.settings.set_infer_width(infer_width);
+        self
+    }
+
+    /// Operator syntax of expressions, like C with [`Dialect::C`]
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.settings.set_dialect(dialect);
+        self
+    }
+
+    /// Read an operand followed by `(`, an identifier or a number as a multiplication,
+    /// like `2(3 + 4)` and `3pi`
+    pub fn implicit_mul(mut self, implicit_mul: bool) -> Self {
+        self.settings.set_implicit_mul(implicit_mul);
+        self
+    }
+
+    /// [`EvaluatorBuilder::build`] fails if the radix of [`OutputMode::Radix`]
+    /// is not in the range of 2 to 36
+    pub fn output(mut self, output: OutputMode) -> Self {
+        let result = self.settings.set_output(output);
+        self.check(result)
+    }
+
+    /// Keep the first invalid setting
+    fn check(mut self, result: Result<(), Error>) -> Self {
+        if let Err(error) = result {
+            self.error.get_or_insert(error);
+        }
+        self
+    }
+
+    /// Build the evaluator, or return the error of the first invalid setting
+    pub fn build(self) -> Result<Evaluator, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut evaluator = Evaluator::empty(self.settings);
+        for set in self.sets {
+            set.install(&mut evaluator);
+        }
+        Ok(evaluator)
+    }
+}
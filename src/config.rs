@@ -0,0 +1,198 @@
+//! Definitions of functions and infix operators, loaded by [`Evaluator::load_config`]
+//!
+//! ```text
+//! # comment
+//! fn avg(a, b) = (a + b) / 2
+//! infix <+> avg 4 left
+//! set output auto
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    expr::{
+        context::{DivisionMode, EvalContext, OutputMode, OverflowPolicy},
+        function::Function,
+        operand::Operand,
+        operator::{Associativity, Signature},
+        Expr,
+    },
+    lex::{check_radix, Dialect},
+    Error, Evaluation, Evaluator,
+};
+
+/// Maximum number of nested calls of functions defined by expressions
+const MAX_DEPTH: usize = 256;
+
+/// Function defined by an expression over its parameters
+struct ExprFunction {
+    name: String,
+    params: Vec<String>,
+    expr: Expr,
+    doc: String,
+}
+
+impl Function for ExprFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::Fixed(self.params.len())
+    }
+
+    fn doc(&self) -> &str {
+        &self.doc
+    }
+
+    /// The expression may call impure functions registered later
+    fn is_pure(&self) -> bool {
+        false
+    }
+
+    fn call(&self, ctx: &EvalContext, args: Vec<Operand>) -> Result<Operand, Error> {
+        if ctx.depth >= MAX_DEPTH {
+            Err(Error::Custom(format!(
+                "{} exceeds the maximum call depth",
+                self.name
+            )))?;
+        }
+        let bindings: HashMap<String, Operand> = self.params.iter().cloned().zip(args).collect();
+        self.expr.accept(&mut Evaluation {
+            ctx: EvalContext {
+                evaluator: ctx.evaluator,
+                bindings: &bindings,
+                depth: ctx.depth + 1,
+            },
+        })
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Evaluator {
+    /// Define functions and infix operators, one per line:
+    /// - `fn NAME(PARAM, ...) = EXPR` defines a function evaluating the expression
+    /// - `infix SYMBOL FUNCTION PRECEDENCE [left|right]` registers an infix operator,
+    ///   see [`Evaluator::register_infix`]
+    /// - `set KEY VALUE` changes a setting:
+    ///   `width` (1 to 128), `overflow` (error, wrap or saturate),
+    ///   `division` (truncate, floor or float), `output` (decimal, auto or a radix)
+    ///   `ibase` (radix of numbers without prefix), `infer_width` (on or off)
+    ///   `dialect` (rcal or c) and `implicit_mul` (on or off)
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    /// Definitions can use the ones before them
+    ///
+    /// ```
+    /// use rcal::{expr::operand::Operand, Evaluator};
+    ///
+    /// # #[cfg(all(feature = "arithmetic", feature = "bitwise"))] {
+    /// let mut evaluator = Evaluator::default();
+    /// evaluator
+    ///     .load_config("fn rotl(x, n) = ((x << n) | (x >> (32 - n))) & 0xFFFFFFFF\ninfix rotl rotl 5")
+    ///     .unwrap();
+    /// assert_eq!(evaluator.eval("0x80000001 rotl 4").unwrap(), Operand::Integer(0x18));
+    /// # }
+    /// ```
+    pub fn load_config(&mut self, config: &str) -> Result<(), Error> {
+        for (idx, line) in config.lines().enumerate() {
+            self.load_line(line.trim()).map_err(|error| Error::Config {
+                line: idx + 1,
+                error: Box::new(error),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn load_line(&mut self, line: &str) -> Result<(), Error> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let invalid = || Error::Custom(format!("Invalid definition: {line}"));
+        let (keyword, rest) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        match keyword {
+            "fn" => {
+                let (head, body) = rest.split_once('=').ok_or_else(invalid)?;
+                let (name, params) = head
+                    .trim()
+                    .strip_suffix(')')
+                    .and_then(|head| head.split_once('('))
+                    .ok_or_else(invalid)?;
+                let name = name.trim();
+                let params: Vec<String> = params
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|param| !param.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if !is_identifier(name) || !params.iter().all(|param| is_identifier(param)) {
+                    Err(invalid())?;
+                }
+                let expr = self.parse(body.trim())?;
+                let doc = format!("{name}({}) = {expr}", params.join(", "));
+                self.register_function(Box::new(ExprFunction {
+                    name: name.to_string(),
+                    params,
+                    expr,
+                    doc,
+                }));
+            }
+            "infix" => {
+                let fields: Vec<&str> = rest.split_whitespace().collect();
+                let (symbol, function, precedence, associativity) = match fields[..] {
+                    [symbol, function, precedence] => (symbol, function, precedence, "left"),
+                    [symbol, function, precedence, associativity] => {
+                        (symbol, function, precedence, associativity)
+                    }
+                    _ => Err(invalid())?,
+                };
+                let precedence = precedence.parse().map_err(|_| invalid())?;
+                let associativity = match associativity {
+                    "left" => Associativity::Left,
+                    "right" => Associativity::Right,
+                    _ => Err(invalid())?,
+                };
+                self.register_infix(symbol, function, precedence, associativity)?;
+            }
+            "set" => {
+                let (key, value) = rest.split_once(char::is_whitespace).ok_or_else(invalid)?;
+                let settings = self.settings_mut();
+                match (key, value.trim()) {
+                    ("width", width) => {
+                        let width = width.parse().map_err(|_| invalid())?;
+                        settings.set_default_width(width).map_err(|_| invalid())?;
+                    }
+                    ("overflow", "error") => settings.set_overflow(OverflowPolicy::Error),
+                    ("overflow", "wrap") => settings.set_overflow(OverflowPolicy::Wrap),
+                    ("overflow", "saturate") => settings.set_overflow(OverflowPolicy::Saturate),
+                    ("division", "truncate") => settings.set_division(DivisionMode::Truncate),
+                    ("division", "floor") => settings.set_division(DivisionMode::Floor),
+                    ("division", "float") => settings.set_division(DivisionMode::Float),
+                    ("output", "decimal") => settings.set_output(OutputMode::Decimal)?,
+                    ("output", "auto") => settings.set_output(OutputMode::Auto)?,
+                    ("output", radix) => {
+                        let radix = radix.parse().map_err(|_| invalid())?;
+                        settings.set_output(OutputMode::Radix(check_radix(radix)?))?;
+                    }
+                    ("ibase", radix) => {
+                        let radix = radix.parse().map_err(|_| invalid())?;
+                        settings.set_ibase(check_radix(radix)?)?;
+                    }
+                    ("infer_width", "on") => settings.set_infer_width(true),
+                    ("infer_width", "off") => settings.set_infer_width(false),
+                    ("dialect", "rcal") => settings.set_dialect(Dialect::Rcal),
+                    ("dialect", "c") => settings.set_dialect(Dialect::C),
+                    ("implicit_mul", "on") => settings.set_implicit_mul(true),
+                    ("implicit_mul", "off") => settings.set_implicit_mul(false),
+                    _ => Err(invalid())?,
+                }
+            }
+            _ => Err(invalid())?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+use std::cmp::Ordering;
+
+use crate::{
+    expr::{
+        context::{DivisionMode, EvalContext, OverflowPolicy},
+        function::{Example, NativeFn},
+        operand::{Operand, OperandType},
+        operator::{Operator, PureHandler, Signature, SizedHandler},
+        Error,
+    },
+    Evaluator, Float, Integer,
+};
+
+use super::{take, Builtin};
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    let handlers: [(Operator, PureHandler); 14] = [
+        (Operator::Add(None), Box::new(op_add)),
+        (Operator::Minus(None), Box::new(op_minus)),
+        (Operator::Mul(None), Box::new(op_mul)),
+        (Operator::Div, Box::new(op_div)),
+        (Operator::Mod, Box::new(op_mod)),
+        (Operator::Expo, Box::new(op_exp)),
+        (Operator::Negate(None), Box::new(neg)),
+        (Operator::Positive, Box::new(noop)),
+        (
+            Operator::Less,
+            Box::new(|_, operands| compare(operands, |o| o == Some(Ordering::Less))),
+        ),
+        (
+            Operator::LessEqual,
+            Box::new(|_, operands| compare(operands, |o| o.is_some_and(Ordering::is_le))),
+        ),
+        (
+            Operator::Greater,
+            Box::new(|_, operands| compare(operands, |o| o == Some(Ordering::Greater))),
+        ),
+        (
+            Operator::GreaterEqual,
+            Box::new(|_, operands| compare(operands, |o| o.is_some_and(Ordering::is_ge))),
+        ),
+        (
+            Operator::Equal,
+            Box::new(|_, operands| compare(operands, |o| o == Some(Ordering::Equal))),
+        ),
+        (
+            Operator::NotEqual,
+            Box::new(|_, operands| compare(operands, |o| o != Some(Ordering::Equal))),
+        ),
+    ];
+    for (op, handler) in handlers {
+        evaluator.insert_builtin(op, handler);
+    }
+    let sized: [(Operator, SizedHandler); 4] = [
+        (Operator::Negate(None), neg_width),
+        (Operator::Add(None), |width, operands| {
+            wrapping(width, operands, Integer::wrapping_add)
+        }),
+        (Operator::Minus(None), |width, operands| {
+            wrapping(width, operands, Integer::wrapping_sub)
+        }),
+        (Operator::Mul(None), |width, operands| {
+            wrapping(width, operands, Integer::wrapping_mul)
+        }),
+    ];
+    for (op, handler) in sized {
+        evaluator.insert_sized_builtin(op, handler);
+    }
+
+    let functions = [
+        Builtin {
+            name: "wrap_add",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Sum of the first two arguments, wrapped around in the bit width of the third one",
+            examples: &[Example {
+                expr: "wrap_add(0xFFFF, 1, 16)",
+                result: "0",
+            }],
+            call: |args| NativeFn::call(&|a, b, bits| wrap(a, b, bits, Integer::wrapping_add), args),
+        },
+        Builtin {
+            name: "wrap_sub",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Difference of the first two arguments, wrapped around in the bit width of the third one",
+            examples: &[Example {
+                expr: "wrap_sub(0, 1, 8)",
+                result: "255",
+            }],
+            call: |args| NativeFn::call(&|a, b, bits| wrap(a, b, bits, Integer::wrapping_sub), args),
+        },
+        Builtin {
+            name: "wrap_mul",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Product of the first two arguments, wrapped around in the bit width of the third one",
+            examples: &[Example {
+                expr: "wrap_mul(0x80, 3, 8)",
+                result: "128",
+            }],
+            call: |args| NativeFn::call(&|a, b, bits| wrap(a, b, bits, Integer::wrapping_mul), args),
+        },
+        Builtin {
+            name: "sat_add",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Sum of the first two arguments, clamped to the unsigned range of the bit width of the third one",
+            examples: &[Example {
+                expr: "sat_add(250, 10, 8)",
+                result: "255",
+            }],
+            call: |args| {
+                NativeFn::call(&|a, b, bits| saturate(a, b, bits, Integer::saturating_add), args)
+            },
+        },
+        Builtin {
+            name: "sat_sub",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Difference of the first two arguments, clamped to the unsigned range of the bit width of the third one",
+            examples: &[Example {
+                expr: "sat_sub(5, 10, 8)",
+                result: "0",
+            }],
+            call: |args| {
+                NativeFn::call(&|a, b, bits| saturate(a, b, bits, Integer::saturating_sub), args)
+            },
+        },
+        Builtin {
+            name: "sat_mul",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Product of the first two arguments, clamped to the unsigned range of the bit width of the third one",
+            examples: &[Example {
+                expr: "sat_mul(0x100, 0x100, 16)",
+                result: "65535",
+            }],
+            call: |args| {
+                NativeFn::call(&|a, b, bits| saturate(a, b, bits, Integer::saturating_mul), args)
+            },
+        },
+    ];
+    for function in functions {
+        evaluator.register_function(Box::new(function));
+    }
+}
+
+/// Mask of the lowest `width` bits
+fn mask(width: usize) -> u128 {
+    u128::MAX >> (128 - width)
+}
+
+/// Check the bit width argument of the wrapping and saturating functions
+fn check_bits(bits: Integer) -> Result<usize, Error> {
+    match bits {
+        1..=128 => Ok(bits as usize),
+        _ => Err(Error::Custom(format!("Invalid bit width: {bits}"))),
+    }
+}
+
+fn wrap(
+    a: Integer,
+    b: Integer,
+    bits: Integer,
+    op: fn(Integer, Integer) -> Integer,
+) -> Result<Integer, Error> {
+    Ok((op(a, b) as u128 & mask(check_bits(bits)?)) as Integer)
+}
+
+/// Clamp the result to the unsigned range of `bits` bits, up to the maximum of [`Integer`]
+fn saturate(
+    a: Integer,
+    b: Integer,
+    bits: Integer,
+    op: fn(Integer, Integer) -> Integer,
+) -> Result<Integer, Error> {
+    let max = mask(check_bits(bits)?).min(Integer::MAX as u128) as Integer;
+    Ok(op(a, b).clamp(0, max))
+}
+
+/// Integer operation wrapped around in `width` bits, for the operators with bit width hint
+fn wrapping(
+    width: usize,
+    operands: Vec<Operand>,
+    op: fn(Integer, Integer) -> Integer,
+) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    Ok(Operand::Integer(
+        (op(a.as_int()?, b.as_int()?) as u128 & mask(width)) as Integer,
+    ))
+}
+
+/// Apply the overflow policy to the results of an integer operation
+fn overflow(
+    ctx: &EvalContext,
+    checked: Option<Integer>,
+    wrapping: Integer,
+    saturating: Integer,
+) -> Result<Operand, Error> {
+    match ctx.settings().overflow() {
+        OverflowPolicy::Error => checked.ok_or(Error::Overflow),
+        OverflowPolicy::Wrap => Ok(wrapping),
+        OverflowPolicy::Saturate => Ok(saturating),
+    }
+    .map(Operand::Integer)
+}
+
+/// Apply `int` or `float` to the operands, integers are converted to float if the other is a float
+fn numeric(
+    operands: Vec<Operand>,
+    int: impl FnOnce(Integer, Integer) -> Result<Operand, Error>,
+    float: impl FnOnce(Float, Float) -> Float,
+) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    match Operand::upgrade_if_need(a, b)? {
+        (Operand::Integer(a), Operand::Integer(b)) => int(a, b),
+        (Operand::Float(a), Operand::Float(b)) => Ok(Operand::Float(float(a, b))),
+        (a, b) => Err(not_numeric(a, b)),
+    }
+}
+
+/// Error of the operands which are not both numbers
+fn not_numeric(a: Operand, b: Operand) -> Error {
+    let invalid = match a {
+        Operand::Integer(_) | Operand::Float(_) => b,
+        _ => a,
+    };
+    Error::InvalidDataType {
+        expected: vec![OperandType::Integer, OperandType::Float],
+        got: invalid.data_type(),
+    }
+}
+
+/// Comparison of the C dialect, 1 if `holds` for the ordering of the operands and 0 otherwise.
+/// The ordering is `None` if a float operand is NaN
+fn compare(operands: Vec<Operand>, holds: fn(Option<Ordering>) -> bool) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let ordering = match Operand::upgrade_if_need(a, b)? {
+        (Operand::Integer(a), Operand::Integer(b)) => Some(a.cmp(&b)),
+        (Operand::Float(a), Operand::Float(b)) => a.partial_cmp(&b),
+        (a, b) => Err(not_numeric(a, b))?,
+    };
+    Ok(Operand::Integer(holds(ordering) as Integer))
+}
+
+fn noop(_ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a] = take(operands)?;
+    Ok(a)
+}
+
+fn neg(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a] = take(operands)?;
+    match a {
+        Operand::Float(f) => Ok(Operand::Float(-f)),
+        Operand::Integer(i) => overflow(ctx, i.checked_neg(), i.wrapping_neg(), i.saturating_neg()),
+        _ => Err(Error::InvalidDataType {
+            expected: vec![OperandType::Integer, OperandType::Float],
+            got: a.data_type(),
+        }),
+    }
+}
+
+/// Two's complement negation in `width` bits
+fn neg_width(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a] = take(operands)?;
+    let a = a.as_int()? as u128;
+    Ok(Operand::Integer(
+        (a.wrapping_neg() & mask(width)) as Integer,
+    ))
+}
+
+fn op_add(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            overflow(
+                ctx,
+                a.checked_add(b),
+                a.wrapping_add(b),
+                a.saturating_add(b),
+            )
+        },
+        |a, b| a + b,
+    )
+}
+
+fn op_minus(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            overflow(
+                ctx,
+                a.checked_sub(b),
+                a.wrapping_sub(b),
+                a.saturating_sub(b),
+            )
+        },
+        |a, b| a - b,
+    )
+}
+
+fn op_mul(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            overflow(
+                ctx,
+                a.checked_mul(b),
+                a.wrapping_mul(b),
+                a.saturating_mul(b),
+            )
+        },
+        |a, b| a * b,
+    )
+}
+
+fn op_div(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            if b == 0 {
+                Err(Error::DivisionByZero)?;
+            }
+            match ctx.settings().division() {
+                DivisionMode::Float => Ok(Operand::Float(a as Float / b as Float)),
+                DivisionMode::Truncate => overflow(
+                    ctx,
+                    a.checked_div(b),
+                    a.wrapping_div(b),
+                    a.saturating_div(b),
+                ),
+                DivisionMode::Floor => {
+                    let quotient = overflow(
+                        ctx,
+                        a.checked_div(b),
+                        a.wrapping_div(b),
+                        a.saturating_div(b),
+                    )?
+                    .as_int()?;
+                    if a % b != 0 && (a < 0) != (b < 0) {
+                        Ok(Operand::Integer(quotient - 1))
+                    } else {
+                        Ok(Operand::Integer(quotient))
+                    }
+                }
+            }
+        },
+        |a, b| a / b,
+    )
+}
+
+/// The sign of the remainder follows the rounding of the division,
+/// so `a == a / b * b + a % b` holds for integer division
+fn op_mod(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            if b == 0 {
+                Err(Error::DivisionByZero)?;
+            }
+            let remainder = a.wrapping_rem(b);
+            match ctx.settings().division() {
+                DivisionMode::Floor if remainder != 0 && (remainder < 0) != (b < 0) => {
+                    Ok(Operand::Integer(remainder + b))
+                }
+                _ => Ok(Operand::Integer(remainder)),
+            }
+        },
+        |a, b| a % b,
+    )
+}
+
+fn op_exp(ctx: &EvalContext, operands: Vec<Operand>) -> Result<Operand, Error> {
+    numeric(
+        operands,
+        |a, b| {
+            let b = u32::try_from(b).map_err(|_| {
+                Error::Custom("Exp operation for integers only allows u32 as parameter".to_string())
+            })?;
+            overflow(
+                ctx,
+                a.checked_pow(b),
+                a.wrapping_pow(b),
+                a.saturating_pow(b),
+            )
+        },
+        Float::powf,
+    )
+}
@@ -0,0 +1,167 @@
+use std::ops::BitXor;
+
+use crate::{
+    expr::{
+        operand::Operand,
+        operator::{stack_handler, Operator, OperatorAction, SizedHandler},
+        Error,
+    },
+    Evaluator, Integer,
+};
+
+use super::take;
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    let handlers: [(Operator, OperatorAction); 5] = [
+        (Operator::BitOr, Box::new(op_bit_or)),
+        (Operator::LogicalNot, Box::new(op_logical_not)),
+        (Operator::BitAnd, Box::new(op_bit_and)),
+        (Operator::LeftShift(None), Box::new(op_bit_sh_left)),
+        (Operator::RightShift(None), Box::new(op_bit_sh_right)),
+    ];
+    for (op, handler) in handlers {
+        evaluator.insert_builtin(op, stack_handler(handler));
+    }
+    evaluator.insert_builtin(
+        Operator::LogicalShift(None),
+        Box::new(|_, operands| op_logical_sh_right(Integer::BITS as usize, operands)),
+    );
+    let sized: [(Operator, SizedHandler); 7] = [
+        (Operator::BitNot(None), op_bit_not),
+        (Operator::BitXor(None), op_bit_xor),
+        (Operator::LeftShift(None), op_bit_sh_left_width),
+        (Operator::RightShift(None), op_bit_sh_right_width),
+        (Operator::LogicalShift(None), op_logical_sh_right),
+        (Operator::RotateLeft(None), |width, operands| {
+            op_rotate(width, operands, true)
+        }),
+        (Operator::RotateRight(None), |width, operands| {
+            op_rotate(width, operands, false)
+        }),
+    ];
+    for (op, handler) in sized {
+        evaluator.insert_sized_builtin(op, handler);
+    }
+}
+
+/// Shift amount of shifts and rotates
+fn shift_amount(amount: Integer) -> Result<u32, Error> {
+    u32::try_from(amount).map_err(|_| invalid_shift(amount))
+}
+
+fn invalid_shift(amount: Integer) -> Error {
+    Error::Custom(format!("Invalid shift amount {amount}"))
+}
+
+/// Mask of the lowest `width` bits
+fn mask(width: usize) -> u128 {
+    u128::MAX >> (128 - width)
+}
+
+fn op_bit_or(operands: &mut Vec<Operand>) -> Result<(), Error> {
+    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+
+    operands.push(Operand::Integer(a | b));
+
+    Ok(())
+}
+
+fn op_bit_and(operands: &mut Vec<Operand>) -> Result<(), Error> {
+    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+
+    operands.push(Operand::Integer(a & b));
+
+    Ok(())
+}
+
+fn op_bit_sh_right(operands: &mut Vec<Operand>) -> Result<(), Error> {
+    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let shifted = shift_amount(b)
+        .ok()
+        .and_then(|amount| a.checked_shr(amount))
+        .ok_or_else(|| invalid_shift(b))?;
+
+    operands.push(Operand::Integer(shifted));
+
+    Ok(())
+}
+
+fn op_bit_sh_left(operands: &mut Vec<Operand>) -> Result<(), Error> {
+    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
+    let shifted = shift_amount(b)
+        .ok()
+        .and_then(|amount| a.checked_shl(amount))
+        .ok_or_else(|| invalid_shift(b))?;
+
+    operands.push(Operand::Integer(shifted));
+
+    Ok(())
+}
+
+fn op_logical_not(operands: &mut Vec<Operand>) -> Result<(), Error> {
+    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.is_true()?;
+    operands.push(Operand::Integer(!a as Integer));
+
+    Ok(())
+}
+
+fn op_bit_not(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a] = take(operands)?;
+    let a = a.as_int()? as u128;
+    Ok(Operand::Integer((!a & mask(width)) as Integer))
+}
+
+fn op_bit_xor(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let (a, b) = (a.as_int()? as u128, b.as_int()? as u128);
+    Ok(Operand::Integer((a.bitxor(b) & mask(width)) as Integer))
+}
+
+fn op_bit_sh_left_width(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let b = shift_amount(b.as_int()?)?;
+    let shifted = (a.as_int()? as u128).checked_shl(b).unwrap_or(0);
+    Ok(Operand::Integer((shifted & mask(width)) as Integer))
+}
+
+/// Arithmetic right shift of the lowest `width` bits, the sign bit is shifted in
+fn op_bit_sh_right_width(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let b = shift_amount(b.as_int()?)?;
+    // move the sign bit of the width to the sign bit of the integer
+    let unused = Integer::BITS as usize - width;
+    let signed = (a.as_int()? << unused) >> unused;
+    let shifted = signed >> b.min(Integer::BITS - 1);
+    Ok(Operand::Integer((shifted as u128 & mask(width)) as Integer))
+}
+
+/// Logical right shift of the lowest `width` bits, zeros are shifted in
+fn op_logical_sh_right(width: usize, operands: Vec<Operand>) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let b = shift_amount(b.as_int()?)?;
+    let shifted = (a.as_int()? as u128 & mask(width))
+        .checked_shr(b)
+        .unwrap_or(0);
+    Ok(Operand::Integer(shifted as Integer))
+}
+
+fn op_rotate(width: usize, operands: Vec<Operand>, left: bool) -> Result<Operand, Error> {
+    let [a, b] = take(operands)?;
+    let b = shift_amount(b.as_int()?)? % width as u32;
+    let a = a.as_int()? as u128 & mask(width);
+    let b = if left {
+        b
+    } else {
+        (width as u32 - b) % width as u32
+    };
+    let rotated = if b == 0 {
+        a
+    } else {
+        (a << b | a >> (width as u32 - b)) & mask(width)
+    };
+    Ok(Operand::Integer(rotated as Integer))
+}
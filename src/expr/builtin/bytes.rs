@@ -0,0 +1,99 @@
+use crate::{
+    expr::{
+        function::Example,
+        operand::{Operand, OperandType},
+        operator::Signature,
+        Error,
+    },
+    Evaluator, Integer,
+};
+
+use super::{take, Builtin};
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    let functions = [
+        Builtin {
+            name: "ascii",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Convert string to an integer consist of ascii code of the string characters. Or vise versa.",
+            examples: &[
+                Example {
+                    expr: "ascii('_FVH')",
+                    result: "1598445128",
+                },
+                Example {
+                    expr: "ascii(0x5f465648)",
+                    result: "_FVH",
+                },
+            ],
+            call: ascii,
+        },
+        Builtin {
+            name: "rev",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Reverse bytes in an integer or characters in an string",
+            examples: &[
+                Example {
+                    expr: "rev(0x12345678)",
+                    result: "2018915346",
+                },
+                Example {
+                    expr: "rev('abc')",
+                    result: "cba",
+                },
+            ],
+            call: rev,
+        },
+    ];
+    for function in functions {
+        evaluator.register_function(Box::new(function));
+    }
+}
+
+fn ascii(args: Vec<Operand>) -> Result<Operand, Error> {
+    let [operand] = take(args)?;
+    match operand {
+        Operand::String(s) => Ok(Operand::Integer(
+            s.chars()
+                .map(|c| c as u8)
+                .fold(0, |pre, b| (pre << 8) + b as Integer),
+        )),
+        Operand::Integer(int) => {
+            let mut int = int as u128;
+            let mut chars = vec![];
+            while int != 0 {
+                chars.push((int & 0xFF) as u8 as char);
+                int >>= 8;
+            }
+            chars.reverse();
+            Ok(Operand::String(String::from_iter(chars)))
+        }
+        _ => Err(Error::InvalidDataType {
+            expected: vec![OperandType::String, OperandType::Integer],
+            got: operand.data_type(),
+        }),
+    }
+}
+
+fn rev(args: Vec<Operand>) -> Result<Operand, Error> {
+    let [operand] = take(args)?;
+    match operand {
+        Operand::String(s) => Ok(Operand::String(s.chars().rev().collect())),
+        Operand::Integer(int) => {
+            let mut int = int as u128;
+            let mut reversed = 0;
+            while int != 0 {
+                reversed <<= 8;
+                reversed += int & 0xFF;
+                int >>= 8;
+            }
+            Ok(Operand::Integer(reversed as Integer))
+        }
+        _ => Err(Error::InvalidDataType {
+            expected: vec![OperandType::String, OperandType::Integer],
+            got: operand.data_type(),
+        }),
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    expr::{
+        function::{Example, NativeFn},
+        operator::Signature,
+        Error,
+    },
+    Evaluator, Integer,
+};
+
+use super::Builtin;
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    let functions = [
+        Builtin {
+            name: "align_up",
+            aliases: &[],
+            signature: Signature::Fixed(2),
+            doc: "Round the first argument up to a multiple of the second one",
+            examples: &[Example {
+                expr: "align_up(0x1001, 4k)",
+                result: "8192",
+            }],
+            call: |args| NativeFn::call(&|x: Integer, align: Integer| align_up(x, align), args),
+        },
+        Builtin {
+            name: "align_down",
+            aliases: &[],
+            signature: Signature::Fixed(2),
+            doc: "Round the first argument down to a multiple of the second one",
+            examples: &[Example {
+                expr: "align_down(0x1fff, 4k)",
+                result: "4096",
+            }],
+            call: |args| NativeFn::call(&|x: Integer, align: Integer| align_down(x, align), args),
+        },
+        Builtin {
+            name: "genmask",
+            aliases: &[],
+            signature: Signature::Fixed(2),
+            doc: "Mask of the bits from the high bit down to the low bit, like GENMASK of linux",
+            examples: &[Example {
+                expr: "genmask(7, 4)",
+                result: "240",
+            }],
+            call: |args| NativeFn::call(&|high: Integer, low: Integer| genmask(high, low), args),
+        },
+        Builtin {
+            name: "field",
+            aliases: &[],
+            signature: Signature::Fixed(3),
+            doc: "Extract the bits from the high bit down to the low bit of the first argument",
+            examples: &[Example {
+                expr: "field(0x1234, 11, 4)",
+                result: "35",
+            }],
+            call: |args| {
+                NativeFn::call(
+                    &|x: Integer, high: Integer, low: Integer| Ok((x & genmask(high, low)?) >> low),
+                    args,
+                )
+            },
+        },
+    ];
+    for function in functions {
+        evaluator.register_function(Box::new(function));
+    }
+}
+
+fn check_alignment(align: Integer) -> Result<(), Error> {
+    if align <= 0 {
+        Err(Error::Custom(format!("Invalid alignment: {align}")))?;
+    }
+    Ok(())
+}
+
+fn align_up(x: Integer, align: Integer) -> Result<Integer, Error> {
+    check_alignment(align)?;
+    x.checked_add((align - x.rem_euclid(align)) % align)
+        .ok_or(Error::Overflow)
+}
+
+fn align_down(x: Integer, align: Integer) -> Result<Integer, Error> {
+    check_alignment(align)?;
+    Ok(x - x.rem_euclid(align))
+}
+
+fn genmask(high: Integer, low: Integer) -> Result<Integer, Error> {
+    if !(0..128).contains(&high) || !(0..=high).contains(&low) {
+        Err(Error::Custom(format!("Invalid bit range: {high}:{low}")))?;
+    }
+    Ok(((u128::MAX >> (127 - high)) & (u128::MAX << low)) as Integer)
+}
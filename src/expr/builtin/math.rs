@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use crate::{
+    expr::{
+        function::{Example, NativeFn},
+        operand::{Operand, OperandType},
+        operator::Signature,
+        Error,
+    },
+    Evaluator, Float, Integer,
+};
+
+use super::{take, Builtin};
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    evaluator.set_variable("pi", Operand::Float(std::f64::consts::PI));
+    evaluator.set_variable("e", Operand::Float(std::f64::consts::E));
+    let functions = [
+        Builtin {
+            name: "abs",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Absolute value",
+            examples: &[Example {
+                expr: "abs(-3)",
+                result: "3",
+            }],
+            call: abs,
+        },
+        Builtin {
+            name: "min",
+            aliases: &[],
+            signature: Signature::Variadic { min: 1 },
+            doc: "Smallest of the arguments",
+            examples: &[Example {
+                expr: "min(3, 1.5, 2)",
+                result: "1.5",
+            }],
+            call: |args| extremum(args, Ordering::Less),
+        },
+        Builtin {
+            name: "max",
+            aliases: &[],
+            signature: Signature::Variadic { min: 1 },
+            doc: "Largest of the arguments",
+            examples: &[Example {
+                expr: "max(3, 1, 2)",
+                result: "3",
+            }],
+            call: |args| extremum(args, Ordering::Greater),
+        },
+        Builtin {
+            name: "sqrt",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Square root",
+            examples: &[Example {
+                expr: "sqrt(2.25)",
+                result: "1.5",
+            }],
+            call: |args| NativeFn::call(&|x: Float| x.sqrt(), args),
+        },
+        Builtin {
+            name: "floor",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Largest integer less than or equal to the argument",
+            examples: &[Example {
+                expr: "floor(-1.5)",
+                result: "-2",
+            }],
+            call: |args| NativeFn::call(&|x: Float| to_int(x.floor()), args),
+        },
+        Builtin {
+            name: "ceil",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Smallest integer greater than or equal to the argument",
+            examples: &[Example {
+                expr: "ceil(1.2)",
+                result: "2",
+            }],
+            call: |args| NativeFn::call(&|x: Float| to_int(x.ceil()), args),
+        },
+        Builtin {
+            name: "round",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Nearest integer, rounding half away from zero",
+            examples: &[Example {
+                expr: "round(2.5)",
+                result: "3",
+            }],
+            call: |args| NativeFn::call(&|x: Float| to_int(x.round()), args),
+        },
+        Builtin {
+            name: "trunc",
+            aliases: &[],
+            signature: Signature::Fixed(1),
+            doc: "Integer part of the argument",
+            examples: &[Example {
+                expr: "trunc(-1.7)",
+                result: "-1",
+            }],
+            call: |args| NativeFn::call(&|x: Float| to_int(x.trunc()), args),
+        },
+    ];
+    for function in functions {
+        evaluator.register_function(Box::new(function));
+    }
+}
+
+/// Convert an integral float to an integer
+fn to_int(x: Float) -> Result<Integer, Error> {
+    if !x.is_finite() || x < Integer::MIN as Float || x >= Integer::MAX as Float {
+        Err(Error::Overflow)?;
+    }
+    Ok(x as Integer)
+}
+
+fn abs(args: Vec<Operand>) -> Result<Operand, Error> {
+    let [operand] = take(args)?;
+    match operand {
+        Operand::Integer(i) => i.checked_abs().map(Operand::Integer).ok_or(Error::Overflow),
+        Operand::Float(f) => Ok(Operand::Float(f.abs())),
+        _ => Err(Error::InvalidDataType {
+            expected: vec![OperandType::Integer, OperandType::Float],
+            got: operand.data_type(),
+        }),
+    }
+}
+
+/// The argument whose comparison to every other argument is `ordering` or equal
+fn extremum(args: Vec<Operand>, ordering: Ordering) -> Result<Operand, Error> {
+    let mut result: Option<Operand> = None;
+    for arg in args {
+        if !matches!(arg, Operand::Integer(_) | Operand::Float(_)) {
+            Err(Error::InvalidDataType {
+                expected: vec![OperandType::Integer, OperandType::Float],
+                got: arg.data_type(),
+            })?;
+        }
+        let replace = match &result {
+            None => true,
+            Some(current) => match Operand::upgrade_if_need(arg.clone(), current.clone())? {
+                (Operand::Integer(a), Operand::Integer(b)) => a.cmp(&b) == ordering,
+                (Operand::Float(a), Operand::Float(b)) => a.partial_cmp(&b) == Some(ordering),
+                _ => false,
+            },
+        };
+        if replace {
+            result = Some(arg);
+        }
+    }
+    result.ok_or(Error::NotEnoughOperand)
+}
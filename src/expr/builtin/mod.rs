@@ -0,0 +1,132 @@
+//! Handlers and functions installed by [`EvaluatorBuilder`](crate::builder::EvaluatorBuilder),
+//! grouped in sets which can be left out with cargo features
+
+use crate::Evaluator;
+
+use super::{
+    context::EvalContext,
+    function::{Example, Function},
+    operand::Operand,
+    operator::Signature,
+    Error,
+};
+
+#[cfg(feature = "arithmetic")]
+mod arithmetic;
+#[cfg(feature = "bitwise")]
+mod bitwise;
+mod bytes;
+#[cfg(feature = "firmware")]
+mod firmware;
+#[cfg(feature = "math")]
+mod math;
+#[cfg(feature = "strings")]
+mod strings;
+
+/// Set of built-in operators and functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinSet {
+    /// `ascii` and `rev`, which are not behind a cargo feature
+    Bytes,
+    /// `+ - * / % ^^`, the unary `-` and `+` and the comparisons of the C dialect
+    #[cfg(feature = "arithmetic")]
+    Arithmetic,
+    /// `| & ^ ~ << >>`, rotates and the logical NOT `!` of the C dialect
+    #[cfg(feature = "bitwise")]
+    Bitwise,
+    /// `base` and `parse`
+    #[cfg(feature = "strings")]
+    Strings,
+    /// `abs`, `min`, `max`, `sqrt`, rounding functions and the constants `pi` and `e`
+    #[cfg(feature = "math")]
+    Math,
+    /// `align_up`, `align_down`, `genmask` and `field`
+    #[cfg(feature = "firmware")]
+    Firmware,
+}
+
+impl BuiltinSet {
+    /// Every set enabled by the cargo features
+    pub fn standard() -> Vec<BuiltinSet> {
+        vec![
+            BuiltinSet::Bytes,
+            #[cfg(feature = "arithmetic")]
+            BuiltinSet::Arithmetic,
+            #[cfg(feature = "bitwise")]
+            BuiltinSet::Bitwise,
+            #[cfg(feature = "strings")]
+            BuiltinSet::Strings,
+            #[cfg(feature = "math")]
+            BuiltinSet::Math,
+            #[cfg(feature = "firmware")]
+            BuiltinSet::Firmware,
+        ]
+    }
+
+    pub(crate) fn install(self, evaluator: &mut Evaluator) {
+        match self {
+            BuiltinSet::Bytes => bytes::install(evaluator),
+            #[cfg(feature = "arithmetic")]
+            BuiltinSet::Arithmetic => arithmetic::install(evaluator),
+            #[cfg(feature = "bitwise")]
+            BuiltinSet::Bitwise => bitwise::install(evaluator),
+            #[cfg(feature = "strings")]
+            BuiltinSet::Strings => strings::install(evaluator),
+            #[cfg(feature = "math")]
+            BuiltinSet::Math => math::install(evaluator),
+            #[cfg(feature = "firmware")]
+            BuiltinSet::Firmware => firmware::install(evaluator),
+        }
+    }
+}
+
+/// Built-in [`Function`] with static documentation
+pub(crate) struct Builtin {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub signature: Signature,
+    pub doc: &'static str,
+    pub examples: &'static [Example],
+    pub call: fn(Vec<Operand>) -> Result<Operand, Error>,
+}
+
+impl Function for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        self.aliases
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    fn doc(&self) -> &str {
+        self.doc
+    }
+
+    fn examples(&self) -> &[Example] {
+        self.examples
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn call(&self, _ctx: &EvalContext, args: Vec<Operand>) -> Result<Operand, Error> {
+        (self.call)(args)
+    }
+}
+
+/// Take exactly `N` operands
+pub(crate) fn take<const N: usize>(operands: Vec<Operand>) -> Result<[Operand; N], Error> {
+    match operands.len() {
+        len if len < N => Err(Error::NotEnoughOperand),
+        len if len > N => Err(Error::RedundantOperand(len)),
+        _ => Ok(operands
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("length is checked"))),
+    }
+}
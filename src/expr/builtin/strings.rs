@@ -0,0 +1,52 @@
+use crate::{
+    expr::{
+        function::{Example, NativeFn},
+        operator::Signature,
+    },
+    format::NumberFormat,
+    lex::check_radix,
+    Evaluator, Integer,
+};
+
+use super::Builtin;
+
+pub(super) fn install(evaluator: &mut Evaluator) {
+    evaluator.register_function(Box::new(Builtin {
+        name: "base",
+        aliases: &[],
+        signature: Signature::Fixed(2),
+        doc: "Convert an integer to a string of digits in the radix, from 2 to 36",
+        examples: &[Example {
+            expr: "base(255, 36)",
+            result: "73",
+        }],
+        call: |args| {
+            NativeFn::call(
+                &|x: Integer, radix: Integer| {
+                    Ok(NumberFormat::new(check_radix(radix)?)
+                        .uppercase(true)
+                        .format(x))
+                },
+                args,
+            )
+        },
+    }));
+    evaluator.register_function(Box::new(Builtin {
+        name: "parse",
+        aliases: &[],
+        signature: Signature::Fixed(2),
+        doc: "Convert a string of digits in the radix, from 2 to 36, to an integer",
+        examples: &[Example {
+            expr: "parse('zz', 36)",
+            result: "1295",
+        }],
+        call: |args| {
+            NativeFn::call(
+                &|s: String, radix: Integer| {
+                    Ok(Integer::from_str_radix(s.trim(), check_radix(radix)?)?)
+                },
+                args,
+            )
+        },
+    }));
+}
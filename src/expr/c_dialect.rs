@@ -0,0 +1,514 @@
+//! Parser of the C dialect, see [`Dialect::C`](crate::lex::Dialect::C).
+//! Integer expressions get the type C gives them, and their operations are lowered to
+//! width-qualified operators and casts, so `~0u` is `0xFFFFFFFF` and `1 << 31` is negative
+
+use std::ops::Range;
+
+use crate::{
+    lex::{IntegerLiteral, LexToken},
+    Error, Integer,
+};
+
+use super::{
+    operand::{CastType, Operand, Rounding},
+    operator::Operator,
+    spanned, Expr,
+};
+
+/// Type of a C expression, for a 64-bit target where `long` is 64 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CType {
+    Int {
+        width: usize,
+        signed: bool,
+    },
+    Float,
+    /// Variables, function calls and strings, whose type is only known when evaluated
+    Unknown,
+}
+
+const INT: CType = CType::Int {
+    width: 32,
+    signed: true,
+};
+
+impl CType {
+    /// Integer promotion, integers narrower than `int` become `int`
+    fn promote(self) -> CType {
+        match self {
+            CType::Int { width, .. } if width < 32 => INT,
+            ty => ty,
+        }
+    }
+
+    /// Common type of the operands of an arithmetic or bitwise operation
+    fn usual(a: CType, b: CType) -> CType {
+        match (a.promote(), b.promote()) {
+            (CType::Unknown, _) | (_, CType::Unknown) => CType::Unknown,
+            (CType::Float, _) | (_, CType::Float) => CType::Float,
+            (a @ CType::Int { width: wa, signed }, b @ CType::Int { width: wb, .. }) => {
+                // the wider type, or the unsigned one of the same width
+                if wa > wb || (wa == wb && !signed) {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    /// Type of an integer literal, the first of the candidates of its suffix and radix its value fits in
+    fn of_literal(literal: &IntegerLiteral) -> CType {
+        if let Some(width) = literal.width {
+            return CType::Int {
+                width,
+                signed: literal.value < 0,
+            };
+        }
+        let decimal = literal.radix == 10;
+        let candidates: &[(usize, bool)] = match (literal.unsigned, literal.long) {
+            (false, false) if decimal => &[(32, true), (64, true)],
+            (false, false) => &[(32, true), (32, false), (64, true), (64, false)],
+            (false, true) if decimal => &[(64, true)],
+            (false, true) => &[(64, true), (64, false)],
+            (true, false) => &[(32, false), (64, false)],
+            (true, true) => &[(64, false)],
+        };
+        let (width, signed) = candidates
+            .iter()
+            .copied()
+            .find(|&(width, signed)| {
+                let max = u128::MAX >> (128 - width + signed as usize);
+                literal.value as u128 <= max
+            })
+            .unwrap_or((128, !literal.unsigned));
+        CType::Int { width, signed }
+    }
+
+    /// Type of the C type name, like `unsigned long` and `uint8_t`.
+    /// The `uN` and `iN` types of the casts of rcal are accepted too
+    fn from_names(names: &[&str]) -> Option<CType> {
+        let int = |width, signed| Some(CType::Int { width, signed });
+        if let [name] = names {
+            match *name {
+                "float" | "double" => return Some(CType::Float),
+                "size_t" | "uintptr_t" => return int(64, false),
+                "ssize_t" | "ptrdiff_t" | "intptr_t" => return int(64, true),
+                _ => {}
+            }
+            let fixed = name
+                .strip_suffix("_t")
+                .and_then(|name| match name.strip_prefix('u') {
+                    Some(name) => Some((name.strip_prefix("int")?, false)),
+                    None => Some((name.strip_prefix("int")?, true)),
+                });
+            if let Some((width @ ("8" | "16" | "32" | "64"), signed)) = fixed {
+                return int(width.parse().ok()?, signed);
+            }
+            match name.parse() {
+                Ok(CastType::Unsigned(width)) => return int(width, false),
+                Ok(CastType::Signed(width)) => return int(width, true),
+                _ => {}
+            }
+        }
+        let unsigned = names.contains(&"unsigned");
+        let base: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| !matches!(*name, "unsigned" | "signed"))
+            .collect();
+        let width = match base.as_slice() {
+            [] if names.is_empty() => return None,
+            [] | ["int"] => 32,
+            ["char"] => 8,
+            ["short"] | ["short", "int"] => 16,
+            ["long"] | ["long", "int"] | ["long", "long"] | ["long", "long", "int"] => 64,
+            ["long", "double"] if !unsigned => return Some(CType::Float),
+            _ => return None,
+        };
+        int(width, !unsigned)
+    }
+}
+
+/// Convert the expression of type `from` to `to`, with a cast unless all the values of `from` fit in `to`
+fn convert(expr: Expr, from: CType, to: CType) -> Expr {
+    match (from, to) {
+        (
+            CType::Int { width, signed },
+            CType::Int {
+                width: to_width,
+                signed: to_signed,
+            },
+        ) if (signed == to_signed && to_width >= width)
+            || (!signed && to_signed && to_width > width) =>
+        {
+            expr
+        }
+        _ if from == to || from == CType::Unknown || to == CType::Unknown => expr,
+        _ => cast(expr, from, to),
+    }
+}
+
+/// Cast of the expression of type `from` to `to`, floats are truncated like C
+fn cast(expr: Expr, from: CType, to: CType) -> Expr {
+    let ty = match to {
+        CType::Int {
+            width,
+            signed: true,
+        } => CastType::Signed(width),
+        CType::Int {
+            width,
+            signed: false,
+        } => CastType::Unsigned(width),
+        CType::Float => CastType::Float,
+        CType::Unknown => return expr,
+    };
+    // floats, and values of unknown type which may be floats, are truncated to integers
+    let rounding = match (from, ty) {
+        (CType::Int { .. }, _) | (_, CastType::Float) => None,
+        _ => Some(Rounding::Trunc),
+    };
+    unary(Operator::Cast(ty, rounding), expr)
+}
+
+fn unary(op: Operator, operand: Expr) -> Expr {
+    Expr::Unary {
+        op,
+        operand: Box::new(operand),
+    }
+}
+
+fn binary(op: Operator, lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Binary {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+fn conditional(condition: Expr, then: Expr, otherwise: Expr) -> Expr {
+    Expr::Conditional {
+        condition: Box::new(condition),
+        then: Box::new(then),
+        otherwise: Box::new(otherwise),
+    }
+}
+
+fn int(value: Integer) -> Expr {
+    Expr::Literal(Operand::Integer(value))
+}
+
+/// 1 if the expression is not zero and 0 otherwise
+fn truth(expr: Expr) -> Expr {
+    conditional(expr, int(1), int(0))
+}
+
+/// Sign-extend the result of a width-qualified operator, which is unsigned, for signed types
+fn signed(expr: Expr, ty: CType) -> Expr {
+    match ty {
+        CType::Int {
+            width,
+            signed: true,
+        } => unary(Operator::Cast(CastType::Signed(width), None), expr),
+        _ => expr,
+    }
+}
+
+/// Width of the width-qualified operators for the type, the full integer for unknown types
+fn width(ty: CType) -> Option<usize> {
+    match ty {
+        CType::Int { width, .. } => Some(width),
+        CType::Unknown => Some(Integer::BITS as usize),
+        CType::Float => None,
+    }
+}
+
+/// Precedence of the conditional operator `?:`, the loosest of C without assignments and comma
+const CONDITIONAL: usize = 13;
+
+/// Precedence of the C binary operators, lower binds tighter.
+/// Operators of rcal which are not C syntax are rejected
+fn precedence(token: &LexToken) -> Result<Option<usize>, Error> {
+    Ok(Some(match token {
+        LexToken::Mul(None) | LexToken::Div | LexToken::Mod => 3,
+        LexToken::Plus(None) | LexToken::Minus(None) => 4,
+        LexToken::LeftShift(None) | LexToken::RightShift(None) => 5,
+        LexToken::Less | LexToken::LessEqual | LexToken::Greater | LexToken::GreaterEqual => 6,
+        LexToken::Equal | LexToken::NotEqual => 7,
+        LexToken::BitAnd => 8,
+        LexToken::BitXor(None) => 9,
+        LexToken::BitOr => 10,
+        LexToken::LogicalAnd => 11,
+        LexToken::LogicalOr => 12,
+        LexToken::Question => CONDITIONAL,
+        token => {
+            check_c(token)?;
+            return Ok(None);
+        }
+    }))
+}
+
+/// Reject the tokens of rcal which have no meaning in C, like `^^` and bit width hints
+fn check_c(token: &LexToken) -> Result<(), Error> {
+    let rcal_only = matches!(
+        token,
+        LexToken::Expo
+            | LexToken::Fill(_)
+            | LexToken::Infix(_)
+            | LexToken::LogicalShift(_)
+            | LexToken::RotateLeft(_)
+            | LexToken::RotateRight(_)
+            | LexToken::Plus(Some(_))
+            | LexToken::Minus(Some(_))
+            | LexToken::Mul(Some(_))
+            | LexToken::BitXor(Some(_))
+            | LexToken::BitNot(Some(_))
+            | LexToken::LeftShift(Some(_))
+            | LexToken::RightShift(Some(_))
+    );
+    if !rcal_only {
+        return Ok(());
+    }
+    Err(Error::Custom(format!("{token} is not C syntax")))
+}
+
+struct CParser<'a> {
+    tokens: &'a [LexToken],
+    idx: usize,
+}
+
+impl CParser<'_> {
+    fn peek(&self) -> Option<&LexToken> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<LexToken> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: LexToken) -> Result<(), Error> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(Error::ExpectToken(expected, token)),
+            None => Err(Error::UnexpectedEnd),
+        }
+    }
+
+    /// Parse binary operations whose precedence is not lower than `max_precedence`,
+    /// all of them are left associative but the conditional operator
+    fn parse_binary(&mut self, max_precedence: usize) -> Result<(Expr, CType), Error> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(token) = self.peek().cloned() {
+            let Some(precedence) = precedence(&token)? else {
+                break;
+            };
+            if precedence > max_precedence {
+                break;
+            }
+            self.idx += 1;
+            if token == LexToken::Question {
+                lhs = self.parse_conditional(lhs.0)?;
+                continue;
+            }
+            let rhs = self.parse_binary(precedence - 1)?;
+            lhs = self.binary(&token, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    /// Parse the branches of a conditional operator after `?`,
+    /// which are converted to their common type like the operands of arithmetic operations
+    fn parse_conditional(&mut self, condition: Expr) -> Result<(Expr, CType), Error> {
+        let (then, a) = self.parse_binary(usize::MAX)?;
+        self.expect(LexToken::Colon)?;
+        let (otherwise, b) = self.parse_binary(CONDITIONAL)?;
+        let ty = CType::usual(a, b);
+        Ok((
+            conditional(condition, convert(then, a, ty), convert(otherwise, b, ty)),
+            ty,
+        ))
+    }
+
+    fn binary(
+        &self,
+        token: &LexToken,
+        (lhs, a): (Expr, CType),
+        (rhs, b): (Expr, CType),
+    ) -> (Expr, CType) {
+        match token {
+            // the second operand is only evaluated if needed, the result is 1 or 0
+            LexToken::LogicalAnd => return (conditional(lhs, truth(rhs), int(0)), INT),
+            LexToken::LogicalOr => return (conditional(lhs, int(1), truth(rhs)), INT),
+            _ => {}
+        }
+        if let LexToken::LeftShift(_) | LexToken::RightShift(_) = token {
+            let ty = a.promote();
+            let lhs = convert(lhs, a, ty);
+            let op = match (token, ty) {
+                (LexToken::LeftShift(_), ty) => Operator::LeftShift(width(ty)),
+                (_, CType::Int { signed: false, .. }) => Operator::LogicalShift(width(ty)),
+                (_, ty) => Operator::RightShift(width(ty)),
+            };
+            return (signed(binary(op, lhs, rhs), ty), ty);
+        }
+        let ty = CType::usual(a, b);
+        let (lhs, rhs) = (convert(lhs, a, ty), convert(rhs, b, ty));
+        let int_width = match ty {
+            CType::Int { width, .. } => Some(width),
+            _ => None,
+        };
+        let expr = match token {
+            LexToken::Mul(_) => signed(binary(Operator::Mul(int_width), lhs, rhs), ty),
+            LexToken::Plus(_) => signed(binary(Operator::Add(int_width), lhs, rhs), ty),
+            LexToken::Minus(_) => signed(binary(Operator::Minus(int_width), lhs, rhs), ty),
+            LexToken::Div => cast(binary(Operator::Div, lhs, rhs), CType::Unknown, ty),
+            LexToken::Mod => cast(binary(Operator::Mod, lhs, rhs), CType::Unknown, ty),
+            LexToken::BitXor(_) => signed(binary(Operator::BitXor(width(ty)), lhs, rhs), ty),
+            LexToken::BitAnd => binary(Operator::BitAnd, lhs, rhs),
+            LexToken::BitOr => binary(Operator::BitOr, lhs, rhs),
+            token => {
+                let op = Operator::try_from(token.clone())
+                    .unwrap_or_else(|_| unreachable!("{token:?} is a comparison"));
+                return (binary(op, lhs, rhs), INT);
+            }
+        };
+        (expr, ty)
+    }
+
+    /// Parse an operand, with its leading unary operators and casts
+    fn parse_unary(&mut self) -> Result<(Expr, CType), Error> {
+        let token = self.next().ok_or(Error::UnexpectedEnd)?;
+        let literal = |operand, ty| Ok((Expr::Literal(operand), ty));
+        match token {
+            LexToken::Integer(literal) => Ok((
+                Expr::Literal(Operand::Integer(literal.value)),
+                CType::of_literal(&literal),
+            )),
+            LexToken::Float(float) => literal(Operand::Float(float), CType::Float),
+            LexToken::String(s) => literal(Operand::String(s), CType::Unknown),
+            LexToken::Bit(n) => literal(Operand::Integer(1 << n), CType::Unknown),
+            LexToken::OpenParenthesis => {
+                if let Some((ty, len)) = self.peek_cast() {
+                    self.idx += len;
+                    let (operand, from) = self.parse_unary()?;
+                    return Ok((cast(operand, from, ty), ty));
+                }
+                let expr = self.parse_binary(usize::MAX)?;
+                self.expect(LexToken::CloseParenthesis)?;
+                Ok(expr)
+            }
+            LexToken::OpenBracket => {
+                let expr = self.parse_binary(usize::MAX)?;
+                self.expect(LexToken::CloseBracket)?;
+                Ok(expr)
+            }
+            LexToken::Custom(name) => {
+                if self.peek() != Some(&LexToken::OpenParenthesis) {
+                    return Ok((Expr::Variable(name), CType::Unknown));
+                }
+                let args = self.parse_args()?;
+                Ok((Expr::Call { name, args }, CType::Unknown))
+            }
+            LexToken::Minus(None) => {
+                let (operand, ty) = self.parse_unary()?;
+                let ty = ty.promote();
+                let op = match ty {
+                    CType::Int { width, .. } => Operator::Negate(Some(width)),
+                    _ => Operator::Negate(None),
+                };
+                Ok((signed(unary(op, operand), ty), ty))
+            }
+            LexToken::Plus(None) => {
+                let (operand, ty) = self.parse_unary()?;
+                Ok((convert(operand, ty, ty.promote()), ty.promote()))
+            }
+            LexToken::BitNot(None) => {
+                let (operand, ty) = self.parse_unary()?;
+                let ty = ty.promote();
+                let operand = unary(Operator::BitNot(width(ty)), operand);
+                Ok((signed(operand, ty), ty))
+            }
+            LexToken::LogicalNot => {
+                let (operand, _) = self.parse_unary()?;
+                Ok((unary(Operator::LogicalNot, operand), INT))
+            }
+            token => {
+                check_c(&token)?;
+                Err(Error::ExpectOperand(token))
+            }
+        }
+    }
+
+    /// Type name and closing parenthesis of a cast after an open parenthesis,
+    /// with the number of their tokens
+    fn peek_cast(&self) -> Option<(CType, usize)> {
+        let rest = self.tokens.get(self.idx..)?;
+        let len = rest
+            .iter()
+            .position(|token| !matches!(token, LexToken::Custom(_)))?;
+        if rest[len] != LexToken::CloseParenthesis || len == 0 {
+            return None;
+        }
+        let names: Vec<&str> = rest[..len]
+            .iter()
+            .filter_map(|token| match token {
+                LexToken::Custom(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        CType::from_names(&names).map(|ty| (ty, len + 1))
+    }
+
+    /// Parse the parenthesized, comma separated arguments of a function call
+    fn parse_args(&mut self) -> Result<Vec<Expr>, Error> {
+        self.expect(LexToken::OpenParenthesis)?;
+        let mut args = vec![];
+        if self.peek() == Some(&LexToken::CloseParenthesis) {
+            self.idx += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.parse_binary(usize::MAX)?.0);
+            match self.next() {
+                Some(LexToken::Comma) => continue,
+                Some(LexToken::CloseParenthesis) => return Ok(args),
+                Some(token) => Err(Error::ExpectToken(LexToken::CloseParenthesis, token))?,
+                None => Err(Error::UnexpectedEnd)?,
+            }
+        }
+    }
+}
+
+fn parse_typed(tokens: &[LexToken], spans: &[Range<usize>]) -> Result<(Expr, CType), Error> {
+    let mut parser = CParser { tokens, idx: 0 };
+    let result = parser
+        .parse_binary(usize::MAX)
+        .and_then(|expr| match parser.next() {
+            Some(token) => Err(Error::ExpectOperator(token)),
+            None => Ok(expr),
+        });
+    // the last token read is the one the error is about
+    result.map_err(|error| spanned(error, spans, parser.idx.saturating_sub(1)))
+}
+
+/// Parse the tokens as a C expression
+pub fn parse_c_expr(tokens: &[LexToken]) -> Result<Expr, Error> {
+    Ok(parse_typed(tokens, &[])?.0)
+}
+
+/// Parse the tokens as a C expression,
+/// with the span of the token an error is about from their byte ranges in the formula
+pub fn parse_c_expr_spanned(tokens: &[LexToken], spans: &[Range<usize>]) -> Result<Expr, Error> {
+    Ok(parse_typed(tokens, spans)?.0)
+}
+
+/// Bit width of the integer type of the C expression,
+/// `None` for floating point expressions and the ones whose type is only known when evaluated
+pub fn c_type_width(tokens: &[LexToken]) -> Result<Option<usize>, Error> {
+    Ok(match parse_typed(tokens, &[])?.1 {
+        CType::Int { width, .. } => Some(width),
+        CType::Float | CType::Unknown => None,
+    })
+}
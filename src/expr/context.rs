@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::{
+    lex::{check_radix, Dialect},
+    Error, Evaluator, Integer,
+};
+
+use super::operand::Operand;
+
+/// What integer arithmetic does when the result does not fit in an [`Integer`](crate::Integer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Fail with [`Error::Overflow`]
+    #[default]
+    Error,
+    /// Wrap around in two's complement
+    Wrap,
+    /// Clamp to the minimum or maximum integer
+    Saturate,
+}
+
+/// Result of dividing two integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DivisionMode {
+    /// Integer quotient rounded toward zero, like C
+    #[default]
+    Truncate,
+    /// Integer quotient rounded toward negative infinity, like python `//`
+    Floor,
+    /// Floating point quotient
+    Float,
+}
+
+/// Radix results are displayed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Decimal,
+    /// The radix most integer literals are written in, see [`dominant_radix`](crate::lex::dominant_radix)
+    Auto,
+    /// Radix in the range of 2 to 36
+    Radix(u32),
+}
+
+/// Settings of an [`Evaluator`], readable by handlers through [`EvalContext`].
+/// The setters reject the values out of range, so the settings are always valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub(crate) default_width: usize,
+    pub(crate) overflow: OverflowPolicy,
+    pub(crate) division: DivisionMode,
+    pub(crate) output: OutputMode,
+    pub(crate) ibase: u32,
+    pub(crate) infer_width: bool,
+    pub(crate) dialect: Dialect,
+    pub(crate) implicit_mul: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            default_width: 32,
+            overflow: OverflowPolicy::default(),
+            division: DivisionMode::default(),
+            output: OutputMode::default(),
+            ibase: 10,
+            infer_width: false,
+            dialect: Dialect::default(),
+            implicit_mul: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Bit width of operators without bit width hint
+    pub fn default_width(&self) -> usize {
+        self.default_width
+    }
+
+    /// Set the bit width of operators without bit width hint, from 1 to 128
+    ///
+    /// ```
+    /// use rcal::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::default();
+    /// evaluator.settings_mut().set_default_width(16).unwrap();
+    /// assert!(evaluator.settings_mut().set_default_width(0).is_err());
+    /// assert_eq!(evaluator.settings().default_width(), 16);
+    /// ```
+    pub fn set_default_width(&mut self, width: usize) -> Result<(), Error> {
+        if !(1..=128).contains(&width) {
+            Err(Error::Custom(format!(
+                "default width must be in the range of 1 to 128, got {width}"
+            )))?;
+        }
+        self.default_width = width;
+        Ok(())
+    }
+
+    pub fn overflow(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    pub fn set_overflow(&mut self, overflow: OverflowPolicy) {
+        self.overflow = overflow;
+    }
+
+    pub fn division(&self) -> DivisionMode {
+        self.division
+    }
+
+    pub fn set_division(&mut self, division: DivisionMode) {
+        self.division = division;
+    }
+
+    pub fn output(&self) -> OutputMode {
+        self.output
+    }
+
+    /// Set the output mode, whose radix must be in the range of 2 to 36
+    pub fn set_output(&mut self, output: OutputMode) -> Result<(), Error> {
+        if let OutputMode::Radix(radix) = output {
+            check_radix(radix as Integer)?;
+        }
+        self.output = output;
+        Ok(())
+    }
+
+    /// Radix of numbers without prefix, see [`LexOptions`](crate::lex::LexOptions)
+    pub fn ibase(&self) -> u32 {
+        self.ibase
+    }
+
+    /// Set the radix of numbers without prefix, from 2 to 36
+    pub fn set_ibase(&mut self, ibase: u32) -> Result<(), Error> {
+        self.ibase = check_radix(ibase as Integer)?;
+        Ok(())
+    }
+
+    /// Give hexadecimal and binary literals the width of their digits,
+    /// see [`LexOptions`](crate::lex::LexOptions)
+    pub fn infer_width(&self) -> bool {
+        self.infer_width
+    }
+
+    pub fn set_infer_width(&mut self, infer_width: bool) {
+        self.infer_width = infer_width;
+    }
+
+    /// Operator syntax of expressions, see [`Dialect`]
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// Read an operand followed by `(`, an identifier or a number as a multiplication, like `2(3 + 4)`.
+    /// Not available in the C dialect
+    pub fn implicit_mul(&self) -> bool {
+        self.implicit_mul
+    }
+
+    pub fn set_implicit_mul(&mut self, implicit_mul: bool) {
+        self.implicit_mul = implicit_mul;
+    }
+}
+
+/// State of an evaluation, passed to operator and function handlers
+pub struct EvalContext<'a> {
+    pub(crate) evaluator: &'a Evaluator,
+    /// Variables bound for this evaluation, over the variables of the evaluator
+    pub(crate) bindings: &'a HashMap<String, Operand>,
+    /// Number of nested calls of functions defined by expressions
+    pub(crate) depth: usize,
+}
+
+impl EvalContext<'_> {
+    pub fn settings(&self) -> &Settings {
+        self.evaluator.settings()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Operand> {
+        self.bindings
+            .get(name)
+            .or_else(|| self.evaluator.variables().get(name))
+    }
+
+    /// Call another registered function
+    pub fn call(&self, name: &str, args: Vec<Operand>) -> Result<Operand, Error> {
+        self.evaluator.call(self, name, args)
+    }
+}
@@ -0,0 +1,217 @@
+use std::marker::PhantomData;
+
+use crate::{Float, Integer};
+
+use super::{
+    context::EvalContext,
+    operand::{Operand, OperandType},
+    operator::{RegisteredHandler, Signature},
+    Error,
+};
+
+/// Usage example of a function, checked by the tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    pub expr: &'static str,
+    /// Display of the result
+    pub result: &'static str,
+}
+
+/// A function callable from expressions, like `rev(0x1234)`
+pub trait Function: Send + Sync {
+    fn name(&self) -> &str;
+    /// Other names the function can be called with
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+    fn signature(&self) -> Signature;
+    /// One-line description
+    fn doc(&self) -> &str {
+        ""
+    }
+    fn examples(&self) -> &[Example] {
+        &[]
+    }
+    /// Whether the result only depends on the arguments,
+    /// so calls with literal arguments can be folded at compile time.
+    /// False unless overridden, since a function with state must not be folded
+    fn is_pure(&self) -> bool {
+        false
+    }
+    /// Called with the evaluated arguments, whose count is already checked against the signature
+    fn call(&self, ctx: &EvalContext, args: Vec<Operand>) -> Result<Operand, Error>;
+}
+
+/// [`Function`] of a [`Handler`](super::operator::Handler) or a [`PureHandler`](super::operator::PureHandler)
+pub(crate) struct HandlerFunction {
+    pub name: String,
+    pub signature: Signature,
+    pub handler: RegisteredHandler,
+}
+
+impl Function for HandlerFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    fn is_pure(&self) -> bool {
+        self.handler.is_pure()
+    }
+
+    fn call(&self, ctx: &EvalContext, args: Vec<Operand>) -> Result<Operand, Error> {
+        self.handler.call(&self.name, ctx, args)
+    }
+}
+
+/// Conversion of an argument into a parameter of a native function
+pub trait FromOperand: Sized {
+    fn from_operand(operand: Operand) -> Result<Self, Error>;
+}
+
+impl FromOperand for Operand {
+    fn from_operand(operand: Operand) -> Result<Self, Error> {
+        Ok(operand)
+    }
+}
+
+impl FromOperand for Integer {
+    fn from_operand(operand: Operand) -> Result<Self, Error> {
+        operand.as_int()
+    }
+}
+
+/// Integers are converted to floating point numbers
+impl FromOperand for Float {
+    fn from_operand(operand: Operand) -> Result<Self, Error> {
+        operand.to_float()?.as_float()
+    }
+}
+
+impl FromOperand for String {
+    fn from_operand(operand: Operand) -> Result<Self, Error> {
+        match operand {
+            Operand::String(s) => Ok(s),
+            _ => Err(Error::InvalidDataType {
+                expected: vec![OperandType::String],
+                got: operand.data_type(),
+            }),
+        }
+    }
+}
+
+/// Conversion of the return value of a native function into the result of the call
+pub trait IntoOperandResult {
+    fn into_operand_result(self) -> Result<Operand, Error>;
+}
+
+macro_rules! impl_into_operand_result {
+    ($($ty:ty => $variant:expr),*) => {
+        $(
+            impl IntoOperandResult for $ty {
+                fn into_operand_result(self) -> Result<Operand, Error> {
+                    Ok($variant(self))
+                }
+            }
+
+            impl IntoOperandResult for Result<$ty, Error> {
+                fn into_operand_result(self) -> Result<Operand, Error> {
+                    self.map($variant)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_operand_result!(
+    Operand => std::convert::identity,
+    Integer => Operand::Integer,
+    Float => Operand::Float,
+    String => Operand::String
+);
+
+/// Closures with typed parameters and return value, `Args` is the tuple of the parameter types
+pub trait NativeFn<Args> {
+    fn arity(&self) -> usize;
+    fn call(&self, args: Vec<Operand>) -> Result<Operand, Error>;
+}
+
+macro_rules! impl_native_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> NativeFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: IntoOperandResult,
+            $($arg: FromOperand),*
+        {
+            fn arity(&self) -> usize {
+                <[&str]>::len(&[$(stringify!($arg)),*])
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: Vec<Operand>) -> Result<Operand, Error> {
+                let mut args = args.into_iter();
+                $(let $arg = $arg::from_operand(args.next().ok_or(Error::NotEnoughOperand)?)?;)*
+                self($($arg),*).into_operand_result()
+            }
+        }
+    };
+}
+
+impl_native_fn!();
+impl_native_fn!(A);
+impl_native_fn!(A, B);
+impl_native_fn!(A, B, C);
+impl_native_fn!(A, B, C, D);
+impl_native_fn!(A, B, C, D, E);
+impl_native_fn!(A, B, C, D, E, G);
+
+/// [`Function`] of a [`NativeFn`] registered by [`Evaluator::register_fn`](crate::Evaluator::register_fn)
+pub(crate) struct NativeFunction<F, Args> {
+    pub name: String,
+    pub function: F,
+    pub args: PhantomData<fn(Args)>,
+    /// Registered by [`Evaluator::register_pure_fn`](crate::Evaluator::register_pure_fn)
+    pub pure: bool,
+}
+
+impl<F: NativeFn<Args> + Send + Sync, Args> Function for NativeFunction<F, Args> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::Fixed(self.function.arity())
+    }
+
+    fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    fn call(&self, _ctx: &EvalContext, args: Vec<Operand>) -> Result<Operand, Error> {
+        self.function.call(args)
+    }
+}
+
+#[cfg(all(test, feature = "arithmetic"))]
+mod test {
+    use crate::Evaluator;
+
+    #[test]
+    fn test_examples() {
+        let evaluator = Evaluator::default();
+        for function in evaluator.functions() {
+            for example in function.examples() {
+                assert_eq!(
+                    evaluator.eval(example.expr).unwrap().to_string(),
+                    example.result,
+                    "{}",
+                    example.expr
+                );
+            }
+        }
+    }
+}
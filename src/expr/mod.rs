@@ -1,201 +1,501 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, ops::Range};
 
-use operand::Operand;
-use operator::Operator;
+use operand::{CastType, Operand, Rounding};
+use operator::{Associativity, InfixOperator, Operator};
 
-use crate::{lex::LexToken, Error};
+use crate::{lex::LexToken, Error, Integer};
 
+pub mod builtin;
+pub mod c_dialect;
+pub mod context;
+pub mod function;
 pub mod operand;
 pub mod operator;
 
-#[derive(Debug, Clone)]
-pub enum ExprToken {
-    Operator(Operator),
-    Operand(Operand),
+/// Syntax tree of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Operand),
+    /// Free variable, bound at evaluation
+    Variable(String),
+    Unary {
+        op: Operator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: Operator,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `condition ? then : otherwise` of the C dialect, only the chosen branch is evaluated
+    Conditional {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
 }
 
-impl Display for ExprToken {
+/// Walks through an [`Expr`].
+/// Each method is responsible for visiting the children of its node, by calling [`Expr::accept`] on them
+pub trait Visitor {
+    type Output;
+
+    fn visit_literal(&mut self, operand: &Operand) -> Self::Output;
+    fn visit_variable(&mut self, name: &str) -> Self::Output;
+    fn visit_unary(&mut self, op: &Operator, operand: &Expr) -> Self::Output;
+    fn visit_binary(&mut self, op: &Operator, lhs: &Expr, rhs: &Expr) -> Self::Output;
+    fn visit_call(&mut self, name: &str, args: &[Expr]) -> Self::Output;
+    fn visit_conditional(
+        &mut self,
+        condition: &Expr,
+        then: &Expr,
+        otherwise: &Expr,
+    ) -> Self::Output;
+}
+
+impl Expr {
+    pub fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
+        match self {
+            Expr::Literal(operand) => visitor.visit_literal(operand),
+            Expr::Variable(name) => visitor.visit_variable(name),
+            Expr::Unary { op, operand } => visitor.visit_unary(op, operand),
+            Expr::Binary { op, lhs, rhs } => visitor.visit_binary(op, lhs, rhs),
+            Expr::Call { name, args } => visitor.visit_call(name, args),
+            Expr::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => visitor.visit_conditional(condition, then, otherwise),
+        }
+    }
+}
+
+/// Prints the expression with every binary operation and negative literal parenthesized.
+/// The output parses back into an expression of the same value, and into the same tree
+/// but for negative literals, which come back as the negation of their magnitude.
+/// Conditionals are printed as `(c ? a : b)`, which only the C dialect reads
+impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ExprToken::Operand(op) => write!(f, "{op}"),
-            ExprToken::Operator(op) => write!(f, "{op}"),
+            // the magnitude of the smallest integer is not a literal
+            Expr::Literal(Operand::Integer(Integer::MIN)) => write!(f, "(-{} - 1)", Integer::MAX),
+            Expr::Literal(Operand::Integer(int)) if *int < 0 => write!(f, "({int})"),
+            Expr::Literal(Operand::Float(float)) if float.is_sign_negative() => {
+                write!(f, "({float:?})")
+            }
+            Expr::Literal(Operand::Float(float)) => write!(f, "{float:?}"),
+            Expr::Literal(Operand::String(s)) => write!(f, "\"{s}\""),
+            Expr::Literal(operand) => write!(f, "{operand}"),
+            Expr::Variable(name) => write!(f, "{name}"),
+            Expr::Unary { op, operand } => write!(f, "{op}{operand}"),
+            Expr::Binary { op, lhs, rhs } => write!(f, "({lhs} {op} {rhs})"),
+            Expr::Call { name, args } => {
+                write!(f, "{name}(")?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            Expr::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => write!(f, "({condition} ? {then} : {otherwise})"),
         }
     }
 }
 
-pub struct LexTokenIter<'a> {
-    inner: &'a [LexToken],
-    idx: usize,
+static NO_INFIX: BTreeMap<String, InfixOperator> = BTreeMap::new();
+
+/// Bit width of a sub-expression, given by its sized literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Width {
+    Unsized,
+    Sized(usize),
+    /// Fill literal `'0` or `'1`, sized by the other operand
+    Fill(bool),
 }
 
-impl<'a> Iterator for LexTokenIter<'a> {
-    type Item = LexToken;
+/// Precedence climbing parser over lexed tokens
+pub struct Parser<'a> {
+    tokens: &'a [LexToken],
+    idx: usize,
+    /// Registered infix operators by their symbols
+    infix: &'a BTreeMap<String, InfixOperator>,
+    /// Width of fill literals without a sized operand
+    default_width: usize,
+    /// Multiply the operands following each other, like `2(3 + 4)`
+    implicit_mul: bool,
+    /// Byte ranges of the tokens in the formula, empty if unknown
+    spans: &'a [Range<usize>],
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.inner.get(self.idx) {
-            Some(item) => {
-                self.idx += 1;
-                Some(item.clone())
-            }
-            None => None,
-        }
+/// The error with the span of the token at `idx`, or the end of the formula past the last token.
+/// Errors with a span already and errors of formulas without spans are kept as they are
+pub(crate) fn spanned(error: Error, spans: &[Range<usize>], idx: usize) -> Error {
+    let span = match (&error, spans.get(idx), spans.last()) {
+        (Error::Spanned { .. }, _, _) | (_, None, None) => return error,
+        (_, Some(span), _) => span.clone(),
+        (_, None, Some(last)) => last.end..last.end,
+    };
+    Error::Spanned {
+        error: Box::new(error),
+        span,
     }
 }
 
-impl<'a> From<&'a [LexToken]> for LexTokenIter<'a> {
-    fn from(value: &'a [LexToken]) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [LexToken]) -> Self {
         Self {
-            inner: value,
+            tokens,
             idx: 0,
+            infix: &NO_INFIX,
+            default_width: 32,
+            implicit_mul: false,
+            spans: &[],
         }
     }
-}
 
-#[derive(Debug, Default, PartialEq, Eq)]
-enum ParseState {
-    #[default]
-    Operand,
-    Operator,
-}
+    /// Parse [`LexToken::Infix`] and identifiers in operator position as the infix operators
+    pub fn with_infix(mut self, infix: &'a BTreeMap<String, InfixOperator>) -> Self {
+        self.infix = infix;
+        self
+    }
+
+    /// Width of fill literals `'0` and `'1` without a sized operand, 32 bits by default
+    pub fn with_default_width(mut self, width: usize) -> Self {
+        self.default_width = width;
+        self
+    }
 
-pub fn parse_expr(tokens: &mut LexTokenIter) -> Result<Vec<ExprToken>, Error> {
-    let mut parse_state = ParseState::default();
-    let mut ret_list = vec![];
+    /// Read an operand followed by `(`, an identifier or a number as a multiplication,
+    /// at the precedence of `*`. The `x` keyword between two operands is then ambiguous
+    pub fn with_implicit_mul(mut self, implicit_mul: bool) -> Self {
+        self.implicit_mul = implicit_mul;
+        self
+    }
 
-    while let Some(token) = tokens.next() {
-        match parse_state {
-            ParseState::Operator => {
-                if let LexToken::CloseParenthesis = token {
-                    return Ok(ret_list);
-                }
-                ret_list.push(ExprToken::Operator(token.try_into()?));
-                parse_state = ParseState::Operand;
+    /// Byte ranges of the tokens in the formula, given to [`Error::Spanned`] around parse errors
+    pub fn with_spans(mut self, spans: &'a [Range<usize>]) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Parse all the tokens into a single expression
+    pub fn parse(mut self) -> Result<Expr, Error> {
+        let result = self
+            .parse_binary(usize::MAX)
+            .and_then(|expr| match self.next() {
+                Some(token) => Err(Error::ExpectOperator(token)),
+                None => Ok(self.resolve(expr, None)),
+            });
+        // the last token read is the one the error is about
+        result.map_err(|error| spanned(error, self.spans, self.idx.saturating_sub(1)))
+    }
+
+    /// The expression with its fill literal sized to `width`, or the default width
+    fn resolve(&self, (expr, width): (Expr, Width), target: Option<usize>) -> Expr {
+        match width {
+            Width::Fill(true) => {
+                let width = target.unwrap_or(self.default_width);
+                Expr::Literal(Operand::Integer((u128::MAX >> (128 - width)) as Integer))
             }
-            ParseState::Operand => match token {
-                LexToken::Float(float) => {
-                    ret_list.push(ExprToken::Operand(Operand::Float(float)));
-                    parse_state = ParseState::Operator;
-                }
-                LexToken::Integer(int) => {
-                    ret_list.push(ExprToken::Operand(Operand::Integer(int)));
-                    parse_state = ParseState::Operator;
-                }
-                LexToken::Minus => {
-                    ret_list.push(ExprToken::Operator(Operator::Negate));
-                }
-                LexToken::Plus => {
-                    ret_list.push(ExprToken::Operator(Operator::Positive));
-                }
-                LexToken::BitNot(width) => {
-                    ret_list.push(ExprToken::Operator(Operator::BitNot(width)));
-                }
-                LexToken::OpenParenthesis => {
-                    ret_list.push(ExprToken::Operator(Operator::OpenParenthesis));
-                    ret_list.extend(parse_expr(tokens)?);
-                    ret_list.push(ExprToken::Operator(Operator::CloseParenthesis));
-                    parse_state = ParseState::Operator;
-                }
-                LexToken::Custom(id) => {
-                    ret_list.push(ExprToken::Operator(Operator::Custom(id.clone())));
-                    ret_list.extend(parse_para(tokens)?);
-                    parse_state = ParseState::Operator;
-                }
-                LexToken::String(s) => {
-                    ret_list.push(ExprToken::Operand(Operand::String(s)));
-                    parse_state = ParseState::Operator;
-                }
-                LexToken::Bit(n) => {
-                    ret_list.push(ExprToken::Operand(Operand::Integer(1 << n)));
-                    parse_state = ParseState::Operator;
+            Width::Fill(false) => Expr::Literal(Operand::Integer(0)),
+            _ => expr,
+        }
+    }
+
+    fn peek(&self) -> Option<&LexToken> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<LexToken> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    /// The next token as an operator
+    fn peek_operator(&self) -> Option<Operator> {
+        match self.peek()? {
+            // `x` is the multiply keyword in operator position, and a variable otherwise
+            LexToken::Custom(name) if name == "x" => Some(Operator::Mul(None)),
+            LexToken::Infix(symbol) | LexToken::Custom(symbol) => {
+                self.infix.get(symbol).cloned().map(Operator::Infix)
+            }
+            token => Operator::try_from(token.clone()).ok(),
+        }
+    }
+
+    /// The operator after an operand with the number of its tokens,
+    /// which is zero for implicit multiplications
+    fn peek_binary(&self) -> Result<Option<(Operator, usize)>, Error> {
+        if !self.implicit_mul {
+            return Ok(self.peek_operator().map(|op| (op, 1)));
+        }
+        match self.peek() {
+            Some(LexToken::Custom(name)) if name == "x" && !self.infix.contains_key(name) => {
+                if self.starts_operand(self.idx + 1) {
+                    let error = Error::AmbiguousJuxtaposition(name.clone());
+                    Err(spanned(error, self.spans, self.idx))?;
                 }
-                _ => Err(Error::ExpectOperand(token.clone()))?,
+                Ok(Some((Operator::Mul(None), 0)))
+            }
+            _ => match self.peek_operator() {
+                Some(op) => Ok(Some((op, 1))),
+                None if self.starts_operand(self.idx) => Ok(Some((Operator::Mul(None), 0))),
+                None => Ok(None),
             },
         }
     }
-    if parse_state != ParseState::Operator {
-        dbg!(parse_state);
-        Err(Error::UnexpectedEnd)?;
+
+    /// Whether the token at `idx` starts an operand without a unary operator
+    fn starts_operand(&self, idx: usize) -> bool {
+        match self.tokens.get(idx) {
+            Some(LexToken::Custom(name)) => name != "as" && !self.infix.contains_key(name),
+            Some(token) => matches!(
+                token,
+                LexToken::Integer(_)
+                    | LexToken::Float(_)
+                    | LexToken::Bit(_)
+                    | LexToken::OpenParenthesis
+                    | LexToken::OpenBracket
+            ),
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, expected: LexToken) -> Result<(), Error> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(Error::ExpectToken(expected, token)),
+            None => Err(Error::UnexpectedEnd),
+        }
     }
-    Ok(ret_list)
-}
 
-pub fn parse_para(tokens: &mut LexTokenIter) -> Result<Vec<ExprToken>, Error> {
-    let token = tokens.next().ok_or(Error::UnexpectedEnd)?;
-    if token.ne(&LexToken::OpenParenthesis) {
-        Err(Error::ExpectToken(LexToken::OpenParenthesis, token.clone()))?;
-    }
-
-    let mut ret_list = vec![];
-    let mut parenthesis_lvl = 0;
-    let mut para_list = vec![];
-    loop {
-        let token = tokens.next().ok_or(Error::UnexpectedEnd)?;
-        match token {
-            LexToken::CloseParenthesis => {
-                if parenthesis_lvl == 0 {
-                    break;
-                } else {
-                    parenthesis_lvl -= 1;
-                    para_list.push(token);
-                }
+    /// Parse binary operations whose precedence is not lower than `max_precedence`.
+    /// The width of sized operands is given to `^` without bit width hint and to fill literals,
+    /// and the width of the left operand to rotates
+    fn parse_binary(&mut self, max_precedence: usize) -> Result<(Expr, Width), Error> {
+        let lhs = self.parse_unary()?;
+        self.parse_operations(lhs, max_precedence)
+    }
+
+    /// Parse the binary operations after the operand `lhs`, see [`Parser::parse_binary`]
+    fn parse_operations(
+        &mut self,
+        mut lhs: (Expr, Width),
+        max_precedence: usize,
+    ) -> Result<(Expr, Width), Error> {
+        while let Some((op, len)) = self.peek_binary()? {
+            let precedence = op.precedence();
+            if !op.is_binary() || precedence > max_precedence {
+                break;
             }
-            LexToken::OpenParenthesis => {
-                parenthesis_lvl += 1;
-                para_list.push(token);
+            self.idx += len;
+            let rhs = match op.associativity() {
+                Associativity::Left => self.parse_binary(precedence - 1)?,
+                Associativity::Right => self.parse_binary(precedence)?,
+            };
+            let width = match (lhs.1, rhs.1) {
+                (Width::Sized(a), Width::Sized(b)) => Some(a.max(b)),
+                (Width::Sized(width), _) | (_, Width::Sized(width)) => Some(width),
+                _ => None,
+            };
+            let op = match (op, width, lhs.1) {
+                (Operator::BitXor(None), Some(width), _) => Operator::BitXor(Some(width)),
+                (Operator::RotateLeft(None), _, Width::Sized(width)) => {
+                    Operator::RotateLeft(Some(width))
+                }
+                (Operator::RotateRight(None), _, Width::Sized(width)) => {
+                    Operator::RotateRight(Some(width))
+                }
+                (op, _, _) => op,
+            };
+            let sized = match (&op, width) {
+                (Operator::Infix(_), _) | (_, None) => Width::Unsized,
+                (_, Some(width)) => Width::Sized(width),
+            };
+            lhs = (
+                Expr::Binary {
+                    op,
+                    lhs: Box::new(self.resolve(lhs, width)),
+                    rhs: Box::new(self.resolve(rhs, width)),
+                },
+                sized,
+            );
+        }
+        Ok(lhs)
+    }
+
+    /// Parse an operand, with its leading unary operators and trailing `as` casts,
+    /// which apply to the result of the unary operators like in Rust
+    fn parse_unary(&mut self) -> Result<(Expr, Width), Error> {
+        let mut operand = self.parse_prefix()?;
+        while matches!(self.peek(), Some(LexToken::Custom(keyword)) if keyword == "as") {
+            self.idx += 1;
+            let ty = match self.next() {
+                Some(LexToken::Custom(ty)) => ty,
+                Some(token) => Err(Error::ExpectOperand(token))?,
+                None => Err(Error::UnexpectedEnd)?,
+            };
+            let rounding = match self.peek() {
+                Some(LexToken::Custom(rounding)) => rounding.parse().ok(),
+                _ => None,
+            };
+            if rounding.is_some() {
+                self.idx += 1;
             }
-            _ => para_list.push(token),
+            operand = self.cast(ty.parse()?, rounding, operand)?;
         }
+        Ok(operand)
     }
-    para_list
-        .split(|tk| *tk == LexToken::Comma)
-        .try_for_each(|para_tk| {
-            ret_list.extend(parse_expr(&mut para_tk.into())?);
-            Ok::<_, Error>(())
-        })?;
 
-    Ok(ret_list)
-}
+    /// `(type)` or `(type rounding)` after an open parenthesis, when followed by an operand
+    fn peek_cast(&self) -> Option<(CastType, Option<Rounding>, usize)> {
+        let rest = self.tokens.get(self.idx..)?;
+        let (ty, rounding, len) = match rest {
+            [LexToken::Custom(ty), LexToken::Custom(rounding), LexToken::CloseParenthesis, ..] => {
+                (ty, Some(rounding.parse().ok()?), 3)
+            }
+            [LexToken::Custom(ty), LexToken::CloseParenthesis, ..] => (ty, None, 2),
+            _ => return None,
+        };
+        let starts_operand = matches!(
+            rest.get(len)?,
+            LexToken::Integer(_)
+                | LexToken::Float(_)
+                | LexToken::String(_)
+                | LexToken::Bit(_)
+                | LexToken::Fill(_)
+                | LexToken::Custom(_)
+                | LexToken::OpenParenthesis
+                | LexToken::OpenBracket
+                | LexToken::Minus(None)
+                | LexToken::Plus(None)
+                | LexToken::BitNot(_)
+        );
+        Some((ty.parse().ok()?, rounding, len)).filter(|_| starts_operand)
+    }
 
-pub fn to_suffix(src: &[ExprToken]) -> Vec<ExprToken> {
-    let mut list = Vec::with_capacity(src.len());
-    let mut op_stack: Vec<Operator> = vec![];
-
-    for token in src {
-        match token {
-            ExprToken::Operand(_) => list.push(token.clone()),
-            ExprToken::Operator(op) => {
-                if *op == Operator::CloseParenthesis {
-                    while let Some(op) = op_stack.pop() {
-                        if op == Operator::OpenParenthesis {
-                            break;
-                        } else {
-                            list.push(ExprToken::Operator(op));
-                        }
-                    }
-                } else if *op == Operator::OpenParenthesis {
-                    op_stack.push(op.clone());
-                } else {
-                    while let Some(top) = op_stack.last() {
-                        if top.precedence() > op.precedence() || op.precedence() == 0 {
-                            break;
-                        }
-                        list.push(ExprToken::Operator(op_stack.pop().unwrap()));
-                    }
-                    op_stack.push(op.clone());
+    /// Cast of the operand, fill literals are sized to the width of the type
+    fn cast(
+        &self,
+        ty: CastType,
+        rounding: Option<Rounding>,
+        operand: (Expr, Width),
+    ) -> Result<(Expr, Width), Error> {
+        if rounding.is_some() && !ty.is_integer() {
+            Err(Error::Custom(format!(
+                "Rounding mode only applies to casts to integers, not to {ty}"
+            )))?;
+        }
+        Ok((
+            Expr::Unary {
+                op: Operator::Cast(ty, rounding),
+                operand: Box::new(self.resolve(operand, ty.width())),
+            },
+            ty.width().map_or(Width::Unsized, Width::Sized),
+        ))
+    }
+
+    /// Parse an operand, with its leading unary operators and C-style casts
+    fn parse_prefix(&mut self) -> Result<(Expr, Width), Error> {
+        let token = self.next().ok_or(Error::UnexpectedEnd)?;
+        let literal = |operand| Ok((Expr::Literal(operand), Width::Unsized));
+        let op = match token {
+            LexToken::Integer(literal) => {
+                let width = literal.width.map_or(Width::Unsized, Width::Sized);
+                return Ok((Expr::Literal(Operand::Integer(literal.value)), width));
+            }
+            LexToken::Fill(bit) => {
+                return Ok((Expr::Literal(Operand::Integer(0)), Width::Fill(bit)))
+            }
+            LexToken::Float(float) => return literal(Operand::Float(float)),
+            LexToken::String(s) => return literal(Operand::String(s)),
+            LexToken::Bit(n) => return literal(Operand::Integer(1 << n)),
+            LexToken::OpenParenthesis => {
+                if let Some((ty, rounding, len)) = self.peek_cast() {
+                    self.idx += len;
+                    let operand =
+                        self.parse_prefix_operand(Operator::Cast(ty, rounding).precedence())?;
+                    return self.cast(ty, rounding, operand);
                 }
+                let expr = self.parse_binary(usize::MAX)?;
+                self.expect(LexToken::CloseParenthesis)?;
+                return Ok(expr);
             }
-        }
+            LexToken::OpenBracket => {
+                let expr = self.parse_binary(usize::MAX)?;
+                self.expect(LexToken::CloseBracket)?;
+                return Ok(expr);
+            }
+            LexToken::Custom(name) => {
+                if self.peek() != Some(&LexToken::OpenParenthesis) {
+                    return Ok((Expr::Variable(name), Width::Unsized));
+                }
+                let args = self.parse_args()?;
+                return Ok((Expr::Call { name, args }, Width::Unsized));
+            }
+            LexToken::Minus(width) => Operator::Negate(width),
+            LexToken::Plus(None) => Operator::Positive,
+            LexToken::BitNot(width) => Operator::BitNot(width),
+            _ => Err(Error::ExpectOperand(token))?,
+        };
+        let operand = self.parse_prefix_operand(op.precedence())?;
+        let (op, width) = match (op, operand.1) {
+            (Operator::BitNot(None), Width::Fill(bit)) => {
+                return Ok((operand.0, Width::Fill(!bit)))
+            }
+            (Operator::BitNot(None), Width::Sized(width)) => {
+                (Operator::BitNot(Some(width)), Width::Sized(width))
+            }
+            (op, Width::Fill(_)) => (op, Width::Unsized),
+            (op, width) => (op, width),
+        };
+        Ok((
+            Expr::Unary {
+                op,
+                operand: Box::new(self.resolve(operand, None)),
+            },
+            width,
+        ))
     }
 
-    while let Some(op) = op_stack.pop() {
-        list.push(ExprToken::Operator(op));
+    /// Parse the operand of a prefix operator, without the `as` casts after it
+    fn parse_prefix_operand(&mut self, precedence: usize) -> Result<(Expr, Width), Error> {
+        let operand = self.parse_prefix()?;
+        self.parse_operations(operand, precedence)
     }
-    list
-}
 
-pub fn print_tokens(tokens: &[ExprToken]) {
-    for tk in tokens {
-        print!("{tk} ");
+    /// Parse the parenthesized, comma separated arguments of a function call
+    fn parse_args(&mut self) -> Result<Vec<Expr>, Error> {
+        self.expect(LexToken::OpenParenthesis)?;
+        let mut args = vec![];
+        if self.peek() == Some(&LexToken::CloseParenthesis) {
+            self.idx += 1;
+            return Ok(args);
+        }
+        loop {
+            let arg = self.parse_binary(usize::MAX)?;
+            args.push(self.resolve(arg, None));
+            match self.next() {
+                Some(LexToken::Comma) => continue,
+                Some(LexToken::CloseParenthesis) => return Ok(args),
+                Some(token) => Err(Error::ExpectToken(LexToken::CloseParenthesis, token))?,
+                None => Err(Error::UnexpectedEnd)?,
+            }
+        }
     }
-    println!();
+}
+
+pub fn parse_expr(tokens: &[LexToken]) -> Result<Expr, Error> {
+    Parser::new(tokens).parse()
 }
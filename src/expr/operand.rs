@@ -1,10 +1,16 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-use crate::{Float, Integer};
+use serde::{ser::SerializeStruct as _, Serialize, Serializer};
+
+use crate::{
+    format::{NumberFormat, Sign},
+    lex::{self, LexToken},
+    Float, Integer,
+};
 
 use super::Error;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
 pub enum OperandType {
     Integer,
     Float,
@@ -82,6 +88,18 @@ impl Operand {
         }
     }
 
+    /// Truth value of the conditions of the C dialect, whether the number is not zero
+    pub fn is_true(&self) -> Result<bool, Error> {
+        match self {
+            Operand::Integer(int) => Ok(*int != 0),
+            Operand::Float(float) => Ok(*float != 0.0),
+            _ => Err(Error::InvalidDataType {
+                expected: vec![OperandType::Integer, OperandType::Float],
+                got: self.data_type(),
+            }),
+        }
+    }
+
     pub fn as_string(&self) -> Result<&str, Error> {
         match self {
             Operand::String(s) => Ok(s.as_str()),
@@ -93,6 +111,170 @@ impl Operand {
     }
 }
 
+/// Target type of a cast, like `(u16)x` or `x as float`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CastType {
+    /// `uN`, truncated to the lowest `N` bits
+    Unsigned(usize),
+    /// `iN`, truncated to the lowest `N` bits and sign-extended
+    Signed(usize),
+    /// `integer`, the full [`Integer`]
+    Integer,
+    Float,
+    String,
+}
+
+impl CastType {
+    /// Bit width of the integer types
+    pub fn width(&self) -> Option<usize> {
+        match *self {
+            CastType::Unsigned(width) | CastType::Signed(width) => Some(width),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, CastType::Float | CastType::String)
+    }
+}
+
+impl FromStr for CastType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let width = |digits: &str| match digits.parse() {
+            Ok(width @ 1..=128) if !digits.starts_with(['0', '+']) => Ok(width),
+            _ => Err(Error::Custom(format!("Invalid cast type: {s}"))),
+        };
+        match s {
+            "integer" => Ok(CastType::Integer),
+            "float" => Ok(CastType::Float),
+            "string" => Ok(CastType::String),
+            _ if s.starts_with('u') => Ok(CastType::Unsigned(width(&s[1..])?)),
+            _ if s.starts_with('i') => Ok(CastType::Signed(width(&s[1..])?)),
+            _ => Err(Error::Custom(format!("Invalid cast type: {s}"))),
+        }
+    }
+}
+
+impl Display for CastType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastType::Unsigned(width) => write!(f, "u{width}"),
+            CastType::Signed(width) => write!(f, "i{width}"),
+            CastType::Integer => write!(f, "integer"),
+            CastType::Float => write!(f, "float"),
+            CastType::String => write!(f, "string"),
+        }
+    }
+}
+
+/// Rounding of floats cast to integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    /// To the nearest integer, halfway away from zero
+    Round,
+    /// Toward zero
+    Trunc,
+    Floor,
+    Ceil,
+}
+
+impl Rounding {
+    fn apply(self, float: Float) -> Float {
+        match self {
+            Rounding::Round => float.round(),
+            Rounding::Trunc => float.trunc(),
+            Rounding::Floor => float.floor(),
+            Rounding::Ceil => float.ceil(),
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round" => Ok(Rounding::Round),
+            "trunc" => Ok(Rounding::Trunc),
+            "floor" => Ok(Rounding::Floor),
+            "ceil" => Ok(Rounding::Ceil),
+            _ => Err(Error::Custom(format!("Invalid rounding mode: {s}"))),
+        }
+    }
+}
+
+impl Display for Rounding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rounding::Round => write!(f, "round"),
+            Rounding::Trunc => write!(f, "trunc"),
+            Rounding::Floor => write!(f, "floor"),
+            Rounding::Ceil => write!(f, "ceil"),
+        }
+    }
+}
+
+impl Operand {
+    /// Convert the operand to `ty`.
+    /// Integers are truncated to the width of the type, floats need `rounding` to become integers,
+    /// and strings are parsed as a number literal
+    pub fn cast(self, ty: CastType, rounding: Option<Rounding>) -> Result<Self, Error> {
+        let operand = match (self, ty) {
+            (Operand::String(s), CastType::String) => return Ok(Operand::String(s)),
+            (operand, CastType::String) => return Ok(Operand::String(operand.to_string())),
+            (Operand::String(s), ty) => parse_number(&s)?.cast(ty, rounding)?,
+            (operand, CastType::Float) => operand.to_float()?,
+            (Operand::Float(float), ty) => {
+                let rounding = rounding.ok_or_else(|| {
+                    Error::Custom(format!(
+                        "Cast of float {float} to {ty} needs a rounding mode: round, trunc, floor or ceil"
+                    ))
+                })?;
+                let float = rounding.apply(float);
+                if !float.is_finite()
+                    || float < Integer::MIN as Float
+                    || float >= Integer::MAX as Float
+                {
+                    Err(Error::Overflow)?;
+                }
+                Operand::Integer(float as Integer)
+            }
+            (operand, _) => operand,
+        };
+        Ok(match (operand, ty) {
+            (Operand::Integer(int), CastType::Unsigned(width)) => {
+                Operand::Integer((int as u128 & (u128::MAX >> (128 - width))) as Integer)
+            }
+            (Operand::Integer(int), CastType::Signed(width)) => {
+                let unused = Integer::BITS as usize - width;
+                Operand::Integer((int << unused) >> unused)
+            }
+            (operand, _) => operand,
+        })
+    }
+}
+
+/// Parse a string holding a number literal, with an optional minus sign
+fn parse_number(s: &str) -> Result<Operand, Error> {
+    let invalid = || Error::Custom(format!("Invalid number: {s:?}"));
+    let (negative, digits) = match s.trim().strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.trim()),
+    };
+    let operand = match lex::tokenize(digits).map_err(|_| invalid())?.as_slice() {
+        [LexToken::Integer(literal)] => Operand::Integer(literal.value),
+        [LexToken::Float(float)] => Operand::Float(*float),
+        _ => Err(invalid())?,
+    };
+    Ok(match operand {
+        Operand::Integer(int) if negative => {
+            Operand::Integer(int.checked_neg().ok_or(Error::Overflow)?)
+        }
+        Operand::Float(float) if negative => Operand::Float(-float),
+        operand => operand,
+    })
+}
+
 impl Display for Operand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -102,3 +284,77 @@ impl Display for Operand {
         }
     }
 }
+
+/// Serializes the operand with every representation of its value,
+/// so the output can be consumed without asking for each format separately
+impl Serialize for Operand {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Operand::Integer(int) => {
+                let mut state = serializer.serialize_struct("Operand", 8)?;
+                state.serialize_field("type", &self.data_type())?;
+                state.serialize_field("decimal", &int.to_string())?;
+                // like `--hex`, `--oct` and `--bin`, negative numbers in two's complement
+                let radix = |format: NumberFormat| {
+                    format
+                        .prefix(true)
+                        .uppercase(true)
+                        .sign(Sign::TwosComplement(Integer::BITS))
+                        .format(*int)
+                };
+                state.serialize_field("hex", &radix(NumberFormat::hex()))?;
+                state.serialize_field("octal", &radix(NumberFormat::oct()))?;
+                state.serialize_field("binary", &radix(NumberFormat::bin()))?;
+                state.serialize_field(
+                    "bits",
+                    &(0..Integer::BITS)
+                        .filter(|i| int & (1 << i) != 0)
+                        .collect::<Vec<_>>(),
+                )?;
+                state.serialize_field("ascii", &AsciiView::new(*int))?;
+                state.serialize_field("float", &(*int as Float))?;
+                state.end()
+            }
+            Operand::Float(float) => {
+                let mut state = serializer.serialize_struct("Operand", 3)?;
+                state.serialize_field("type", &self.data_type())?;
+                state.serialize_field("decimal", &float.to_string())?;
+                state.serialize_field("float", float)?;
+                state.end()
+            }
+            Operand::String(s) => {
+                let mut state = serializer.serialize_struct("Operand", 2)?;
+                state.serialize_field("type", &self.data_type())?;
+                state.serialize_field("string", s)?;
+                state.end()
+            }
+        }
+    }
+}
+
+/// The bytes of an integer read as ascii characters, `None` for zero or if any of them is not printable
+#[derive(Serialize)]
+struct AsciiView {
+    big_endian: Option<String>,
+    little_endian: Option<String>,
+}
+
+impl AsciiView {
+    fn new(int: Integer) -> Self {
+        let bytes = (int as u128).to_be_bytes();
+        let bytes = &bytes[bytes.iter().take_while(|b| **b == 0).count()..];
+        if !bytes.is_empty() && bytes.iter().all(|b| (0x20..0x7F).contains(b)) {
+            let big_endian = bytes.iter().map(|b| *b as char).collect::<String>();
+            let little_endian = big_endian.chars().rev().collect();
+            Self {
+                big_endian: Some(big_endian),
+                little_endian: Some(little_endian),
+            }
+        } else {
+            Self {
+                big_endian: None,
+                little_endian: None,
+            }
+        }
+    }
+}
@@ -1,31 +1,64 @@
-use std::{fmt::Display, ops::BitXor};
+use std::{
+    fmt::Display,
+    sync::{Mutex, PoisonError},
+    thread::{self, ThreadId},
+};
 
-use crate::{lex::LexToken, Integer};
+use crate::lex::LexToken;
 
 use super::{
-    operand::{Operand, OperandType},
+    context::EvalContext,
+    operand::{CastType, Operand, Rounding},
     Error,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Operator {
-    Add,
-    Minus,
-    Mul,
+    /// Bit width hint, `None` for arithmetic on the full integer
+    Add(Option<usize>),
+    Minus(Option<usize>),
+    Mul(Option<usize>),
     Div,
-    OpenParenthesis,
-    CloseParenthesis,
     BitOr,
     BitAnd,
-    BitXor(usize),
-    BitNot(usize),
+    /// Bit width hint, `None` for the default width of the evaluator
+    BitXor(Option<usize>),
+    BitNot(Option<usize>),
     Expo,
     Mod,
     Custom(String),
-    RightShift,
-    LeftShift,
-    Negate,
+    /// Bit width hint, `None` for shifts on the full integer
+    RightShift(Option<usize>),
+    LeftShift(Option<usize>),
+    LogicalShift(Option<usize>),
+    /// Bit width hint, `None` for the default width of the evaluator
+    RotateLeft(Option<usize>),
+    RotateRight(Option<usize>),
+    /// Bit width hint, `None` for negation of the full integer
+    Negate(Option<usize>),
     Positive,
+    /// `!` of the C dialect, 1 if the operand is 0 and 0 otherwise
+    LogicalNot,
+    /// Comparisons of the C dialect, 1 if they hold and 0 otherwise
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    /// `(u16)x` or `x as u16`, with the rounding of floats cast to integers
+    Cast(CastType, Option<Rounding>),
+    /// Infix operator registered by [`Evaluator::register_infix`](crate::Evaluator::register_infix)
+    Infix(InfixOperator),
+}
+
+/// Infix operator calling a function with its two operands
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InfixOperator {
+    pub symbol: String,
+    pub function: String,
+    pub precedence: usize,
+    pub associativity: Associativity,
 }
 
 impl TryFrom<LexToken> for Operator {
@@ -36,397 +69,338 @@ impl TryFrom<LexToken> for Operator {
             LexToken::BitNot(w) => Ok(Operator::BitNot(w)),
             LexToken::BitOr => Ok(Operator::BitOr),
             LexToken::BitXor(w) => Ok(Operator::BitXor(w)),
-            LexToken::CloseParenthesis => Ok(Operator::CloseParenthesis),
             LexToken::Div => Ok(Operator::Div),
             LexToken::Expo => Ok(Operator::Expo),
-            LexToken::LeftShift => Ok(Operator::LeftShift),
-            LexToken::Minus => Ok(Operator::Minus),
+            LexToken::LeftShift(w) => Ok(Operator::LeftShift(w)),
+            LexToken::Minus(w) => Ok(Operator::Minus(w)),
             LexToken::Mod => Ok(Operator::Mod),
-            LexToken::Mul => Ok(Operator::Mul),
-            LexToken::OpenParenthesis => Ok(Operator::OpenParenthesis),
-            LexToken::Plus => Ok(Operator::Add),
-            LexToken::RightShift => Ok(Operator::RightShift),
+            LexToken::Mul(w) => Ok(Operator::Mul(w)),
+            LexToken::Plus(w) => Ok(Operator::Add(w)),
+            LexToken::RightShift(w) => Ok(Operator::RightShift(w)),
+            LexToken::LogicalShift(w) => Ok(Operator::LogicalShift(w)),
+            LexToken::RotateLeft(w) => Ok(Operator::RotateLeft(w)),
+            LexToken::RotateRight(w) => Ok(Operator::RotateRight(w)),
+            LexToken::Less => Ok(Operator::Less),
+            LexToken::LessEqual => Ok(Operator::LessEqual),
+            LexToken::Greater => Ok(Operator::Greater),
+            LexToken::GreaterEqual => Ok(Operator::GreaterEqual),
+            LexToken::Equal => Ok(Operator::Equal),
+            LexToken::NotEqual => Ok(Operator::NotEqual),
             _ => Err(Error::ExpectOperator(value)),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
 impl Operator {
-    /// lower number is hight precedence
-    /// unary operators should have a precedence 0
+    /// lower number is higher precedence
+    /// unary operators share the precedence of exponent, so `-2 ^^ 2` is `-(2 ^^ 2)`.
+    /// Comparisons only come from the C dialect, whose parser has its own precedence table
     pub fn precedence(&self) -> usize {
         match self {
-            Self::Custom(_) | Self::BitNot(_) | Self::Negate | Self::Positive => 0,
-            Self::Expo => 2,
-            Self::Mul | Self::Div | Self::Mod => 3,
-            Self::Add | Self::Minus => 4,
-            Self::LeftShift | Self::RightShift => 5,
+            Self::Custom(_) => 0,
+            Self::Expo
+            | Self::BitNot(_)
+            | Self::Negate(_)
+            | Self::Positive
+            | Self::LogicalNot
+            | Self::Cast(..) => 2,
+            Self::Mul(_) | Self::Div | Self::Mod => 3,
+            Self::Add(_) | Self::Minus(_) => 4,
+            Self::LeftShift(_)
+            | Self::RightShift(_)
+            | Self::LogicalShift(_)
+            | Self::RotateLeft(_)
+            | Self::RotateRight(_) => 5,
             Self::BitAnd => 6,
             Self::BitXor(_) => 7,
             Self::BitOr => 8,
-            Self::OpenParenthesis | Self::CloseParenthesis => 9,
+            Self::Less | Self::LessEqual | Self::Greater | Self::GreaterEqual => 9,
+            Self::Equal | Self::NotEqual => 10,
+            Self::Infix(infix) => infix.precedence,
         }
     }
-}
 
-impl Display for Operator {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    pub fn associativity(&self) -> Associativity {
         match self {
-            Operator::Add => write!(f, "+"),
-            Operator::Minus => write!(f, "-"),
-            Operator::Mul => write!(f, "*"),
-            Operator::Div => write!(f, "/"),
-            Operator::OpenParenthesis => write!(f, "("),
-            Operator::CloseParenthesis => write!(f, ")"),
-            Operator::BitOr => write!(f, "|"),
-            Operator::BitAnd => write!(f, "&"),
-            Operator::BitXor(width) => write!(f, "{width}^"),
-            Operator::BitNot(width) => write!(f, "{width}!"),
-            Operator::Expo => write!(f, "^"),
-            Operator::Mod => write!(f, "%"),
-            Operator::Custom(id) => write!(f, "{id}"),
-            Operator::RightShift => write!(f, ">>"),
-            Operator::LeftShift => write!(f, "<<"),
-            Operator::Negate => write!(f, "-"),
-            Operator::Positive => write!(f, "+"),
+            Self::Expo
+            | Self::BitNot(_)
+            | Self::Negate(_)
+            | Self::Positive
+            | Self::LogicalNot
+            | Self::Cast(..) => Associativity::Right,
+            Self::Infix(infix) => infix.associativity,
+            _ => Associativity::Left,
         }
     }
-}
-
-pub type OperatorAction = Box<dyn Fn(&mut Vec<Operand>) -> Result<(), Error>>;
-
-pub fn default_handlers() -> Vec<(Operator, OperatorAction)> {
-    vec![
-        (Operator::Add, Box::new(op_add)),
-        (Operator::Minus, Box::new(op_minus)),
-        (Operator::Mul, Box::new(op_mul)),
-        (Operator::Div, Box::new(op_div)),
-        (Operator::Expo, Box::new(op_exp)),
-        (Operator::BitOr, Box::new(op_bit_or)),
-        (Operator::BitAnd, Box::new(op_bit_and)),
-        (Operator::Mod, Box::new(op_mod)),
-        (Operator::LeftShift, Box::new(op_bit_sh_left)),
-        (Operator::RightShift, Box::new(op_bit_sh_right)),
-        (Operator::BitNot(8), Box::new(op_bit_not_8)),
-        (Operator::BitNot(16), Box::new(op_bit_not_16)),
-        (Operator::BitNot(32), Box::new(op_bit_not_32)),
-        (Operator::BitNot(64), Box::new(op_bit_not_64)),
-        (Operator::BitXor(8), Box::new(op_bit_xor_8)),
-        (Operator::BitXor(16), Box::new(op_bit_xor_16)),
-        (Operator::BitXor(32), Box::new(op_bit_xor_32)),
-        (Operator::BitXor(64), Box::new(op_bit_xor_64)),
-        (Operator::BitXor(128), Box::new(op_bit_xor_128)),
-        (Operator::Custom("ascii".to_string()), Box::new(ascii)),
-        (Operator::Custom("rev".to_string()), Box::new(rev)),
-        (Operator::Negate, Box::new(neg)),
-        (Operator::Positive, Box::new(noop)),
-    ]
-}
-
-#[allow(clippy::ptr_arg)]
-fn noop(_operands: &mut Vec<Operand>) -> Result<(), Error> {
-    Ok(())
-}
 
-fn neg(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let token = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    match token {
-        Operand::Float(f) => operands.push(Operand::Float(f * -1.0)),
-        Operand::Integer(i) => operands.push(Operand::Integer(-i)),
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: token.data_type(),
-        })?,
+    /// Number of operands of the operator.
+    /// Functions accept any number of arguments unless registered with a signature
+    pub fn signature(&self) -> Signature {
+        match self {
+            Self::Custom(_) => Signature::Variadic { min: 0 },
+            _ if self.is_binary() => Signature::Fixed(2),
+            _ => Signature::Fixed(1),
+        }
     }
-    Ok(())
-}
-
-fn op_add(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let (a, b) = Operand::upgrade_if_need(a, b)?;
 
-    let result = match a {
-        Operand::Integer(a) => {
-            let b = b.as_int()?;
-            Operand::Integer(a + b)
-        }
-        Operand::Float(a) => {
-            let b = b.as_float()?;
-            Operand::Float(a + b)
+    /// The operator with its missing bit width hint set to `width`
+    pub fn with_default_width(&self, width: usize) -> Operator {
+        match self {
+            Self::BitXor(None) => Self::BitXor(Some(width)),
+            Self::BitNot(None) => Self::BitNot(Some(width)),
+            Self::RotateLeft(None) => Self::RotateLeft(Some(width)),
+            Self::RotateRight(None) => Self::RotateRight(Some(width)),
+            op => op.clone(),
         }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: a.data_type(),
-        })?,
-    };
-    operands.push(result);
-
-    Ok(())
-}
-
-fn op_minus(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let (a, b) = Operand::upgrade_if_need(a, b)?;
+    }
 
-    let result = match a {
-        Operand::Integer(a) => {
-            let b = b.as_int()?;
-            Operand::Integer(a - b)
-        }
-        Operand::Float(a) => {
-            let b = b.as_float()?;
-            Operand::Float(a - b)
+    /// Bit width hint of the operator
+    pub fn width(&self) -> Option<usize> {
+        match *self {
+            Self::Add(width)
+            | Self::Minus(width)
+            | Self::Mul(width)
+            | Self::BitXor(width)
+            | Self::BitNot(width)
+            | Self::RightShift(width)
+            | Self::LeftShift(width)
+            | Self::LogicalShift(width)
+            | Self::RotateLeft(width)
+            | Self::RotateRight(width)
+            | Self::Negate(width) => width,
+            _ => None,
         }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: a.data_type(),
-        })?,
-    };
-    operands.push(result);
-
-    Ok(())
-}
-
-fn op_mul(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let (a, b) = Operand::upgrade_if_need(a, b)?;
+    }
 
-    let result = match a {
-        Operand::Integer(a) => {
-            let b = b.as_int()?;
-            Operand::Integer(a * b)
-        }
-        Operand::Float(a) => {
-            let b = b.as_float()?;
-            Operand::Float(a * b)
+    /// The operator with its bit width hint replaced by `width`,
+    /// operators without hint are unchanged
+    pub fn with_width(&self, width: Option<usize>) -> Operator {
+        match self {
+            Self::Add(_) => Self::Add(width),
+            Self::Minus(_) => Self::Minus(width),
+            Self::Mul(_) => Self::Mul(width),
+            Self::BitXor(_) => Self::BitXor(width),
+            Self::BitNot(_) => Self::BitNot(width),
+            Self::RightShift(_) => Self::RightShift(width),
+            Self::LeftShift(_) => Self::LeftShift(width),
+            Self::LogicalShift(_) => Self::LogicalShift(width),
+            Self::RotateLeft(_) => Self::RotateLeft(width),
+            Self::RotateRight(_) => Self::RotateRight(width),
+            Self::Negate(_) => Self::Negate(width),
+            op => op.clone(),
         }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: a.data_type(),
-        })?,
-    };
-    operands.push(result);
-
-    Ok(())
-}
-
-fn op_div(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let (a, b) = Operand::upgrade_if_need(a, b)?;
+    }
 
-    let result = match a {
-        Operand::Integer(a) => {
-            let b = b.as_int()?;
-            Operand::Integer(a / b)
-        }
-        Operand::Float(a) => {
-            let b = b.as_float()?;
-            Operand::Float(a / b)
-        }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: a.data_type(),
-        })?,
-    };
-    operands.push(result);
-
-    Ok(())
+    /// Whether the operator takes operands on both sides
+    pub fn is_binary(&self) -> bool {
+        !matches!(
+            self,
+            Self::Custom(_)
+                | Self::BitNot(_)
+                | Self::Negate(_)
+                | Self::Positive
+                | Self::LogicalNot
+                | Self::Cast(..)
+        )
+    }
 }
 
-fn op_exp(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    let (a, b) = Operand::upgrade_if_need(a, b)?;
-
-    let result = match a {
-        Operand::Integer(a) => {
-            let b = b.as_int()?;
-            if b < u32::MIN as Integer || b > u32::MAX as Integer {
-                Err(Error::Custom(
-                    "Exp operation for integers only allows u32 as parameter".to_string(),
-                ))?;
-            }
-            Operand::Integer(a.pow(b as u32))
-        }
-        Operand::Float(a) => {
-            let b = b.as_float()?;
-            Operand::Float(a.powf(b))
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operator::Add(width) => write!(f, "{}+", width_hint(*width)),
+            Operator::Minus(width) => write!(f, "{}-", width_hint(*width)),
+            Operator::Mul(width) => write!(f, "{}*", width_hint(*width)),
+            Operator::Div => write!(f, "/"),
+            Operator::BitOr => write!(f, "|"),
+            Operator::BitAnd => write!(f, "&"),
+            Operator::BitXor(width) => write!(f, "{}^", width_hint(*width)),
+            Operator::BitNot(width) => write!(f, "{}~", width_hint(*width)),
+            Operator::Expo => write!(f, "^^"),
+            Operator::Mod => write!(f, "%"),
+            Operator::Custom(id) => write!(f, "{id}"),
+            Operator::RightShift(width) => write!(f, "{}>>", width_hint(*width)),
+            Operator::LeftShift(width) => write!(f, "{}<<", width_hint(*width)),
+            Operator::LogicalShift(width) => write!(f, "{}>>>", width_hint(*width)),
+            Operator::RotateLeft(width) => write!(f, "{}rol", braced_hint(*width)),
+            Operator::RotateRight(width) => write!(f, "{}ror", braced_hint(*width)),
+            Operator::Negate(width) => write!(f, "{}-", braced_hint(*width)),
+            Operator::Positive => write!(f, "+"),
+            Operator::LogicalNot => write!(f, "!"),
+            Operator::Less => write!(f, "<"),
+            Operator::LessEqual => write!(f, "<="),
+            Operator::Greater => write!(f, ">"),
+            Operator::GreaterEqual => write!(f, ">="),
+            Operator::Equal => write!(f, "=="),
+            Operator::NotEqual => write!(f, "!="),
+            Operator::Cast(ty, None) => write!(f, "({ty})"),
+            Operator::Cast(ty, Some(rounding)) => write!(f, "({ty} {rounding})"),
+            Operator::Infix(infix) => write!(f, "{}", infix.symbol),
         }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::Integer, OperandType::Float],
-            got: a.data_type(),
-        })?,
-    };
-    operands.push(result);
-
-    Ok(())
-}
-
-fn op_bit_or(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-
-    operands.push(Operand::Integer(a | b));
-
-    Ok(())
-}
-
-fn op_bit_and(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-
-    operands.push(Operand::Integer(a & b));
-
-    Ok(())
+    }
 }
 
-fn op_bit_sh_right(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-
-    operands.push(Operand::Integer(a >> b));
-
-    Ok(())
+/// Number of arguments accepted by a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signature {
+    Fixed(usize),
+    /// `required` arguments followed by up to `optional` ones
+    Optional {
+        required: usize,
+        optional: usize,
+    },
+    Variadic {
+        min: usize,
+    },
 }
 
-fn op_bit_sh_left(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-
-    operands.push(Operand::Integer(a << b));
-
-    Ok(())
+impl Signature {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Signature::Fixed(n) => count == n,
+            Signature::Optional { required, optional } => {
+                (required..=required + optional).contains(&count)
+            }
+            Signature::Variadic { min } => count >= min,
+        }
+    }
 }
 
-fn op_mod(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
-
-    operands.push(Operand::Integer(a % b));
-
-    Ok(())
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        match *self {
+            Signature::Fixed(n) => write!(f, "{n} {}", plural(n)),
+            Signature::Optional { required, optional } => write!(
+                f,
+                "{required} to {} {}",
+                required + optional,
+                plural(required + optional)
+            ),
+            Signature::Variadic { min } => write!(f, "at least {min} {}", plural(min)),
+        }
+    }
 }
 
-fn op_bit_not_8(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u8;
-    operands.push(Operand::Integer((!a) as Integer));
-
-    Ok(())
+pub(crate) fn width_hint(width: Option<usize>) -> String {
+    match width {
+        Some(8) => "b".to_string(),
+        Some(16) => "w".to_string(),
+        Some(32) => "dw".to_string(),
+        Some(64) => "l".to_string(),
+        Some(128) => "ll".to_string(),
+        width => braced_hint(width),
+    }
 }
 
-fn op_bit_not_16(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u16;
-    operands.push(Operand::Integer((!a) as Integer));
-
-    Ok(())
+/// Bit width hint of operators without the letter hints, like `{24}`
+pub(crate) fn braced_hint(width: Option<usize>) -> String {
+    width
+        .map(|width| format!("{{{width}}}"))
+        .unwrap_or_default()
 }
 
-fn op_bit_not_32(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u32;
-    operands.push(Operand::Integer((!a) as Integer));
+/// Stack-style handler of an operator or a function.
+/// The operand stack holds exactly the operands of the operation, in order,
+/// so its length is the argument count of the call.
+/// The handler must leave the result as the only operand on the stack
+pub type OperatorAction = Box<dyn Fn(&mut Vec<Operand>) -> Result<(), Error> + Send + Sync>;
 
-    Ok(())
-}
-
-fn op_bit_not_64(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u64;
-    operands.push(Operand::Integer((!a) as Integer));
+/// Handler of an operator or a function, called with the evaluation context and the operands in order.
+/// Handlers may keep state between calls
+pub type Handler = Box<dyn FnMut(&EvalContext, Vec<Operand>) -> Result<Operand, Error> + Send>;
 
-    Ok(())
-}
+/// [`Handler`] without state, which can be called from multiple threads at once
+pub type PureHandler =
+    Box<dyn Fn(&EvalContext, Vec<Operand>) -> Result<Operand, Error> + Send + Sync>;
 
-fn op_bit_xor_8(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u8;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u8;
-    operands.push(Operand::Integer((a.bitxor(b)) as Integer));
+/// Built-in handler of an operator with a bit width hint, called with the width
+pub(crate) type SizedHandler = fn(usize, Vec<Operand>) -> Result<Operand, Error>;
 
-    Ok(())
+/// Registered handler of an operator or a function
+pub(crate) enum RegisteredHandler {
+    /// Called without locking
+    Pure(PureHandler),
+    Stateful(SharedHandler),
 }
 
-fn op_bit_xor_16(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u16;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u16;
-    operands.push(Operand::Integer((a.bitxor(b)) as Integer));
+impl RegisteredHandler {
+    /// Whether the result only depends on the operands
+    pub fn is_pure(&self) -> bool {
+        matches!(self, Self::Pure(_))
+    }
 
-    Ok(())
+    pub fn call(
+        &self,
+        name: &dyn Display,
+        ctx: &EvalContext,
+        args: Vec<Operand>,
+    ) -> Result<Operand, Error> {
+        match self {
+            Self::Pure(handler) => handler(ctx, args),
+            Self::Stateful(handler) => handler.call(name, ctx, args),
+        }
+    }
 }
 
-fn op_bit_xor_32(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u32;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u32;
-    operands.push(Operand::Integer((a.bitxor(b)) as Integer));
-
-    Ok(())
+/// A [`Handler`] callable from multiple threads, one call at a time.
+/// Recursive calls of the handler from itself are rejected instead of deadlocking
+pub(crate) struct SharedHandler {
+    handler: Mutex<Handler>,
+    /// Thread currently running the handler
+    owner: Mutex<Option<ThreadId>>,
 }
 
-fn op_bit_xor_64(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u64;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u64;
-    operands.push(Operand::Integer((a.bitxor(b)) as Integer));
+impl SharedHandler {
+    pub fn new(handler: Handler) -> Self {
+        Self {
+            handler: Mutex::new(handler),
+            owner: Mutex::new(None),
+        }
+    }
 
-    Ok(())
+    pub fn call(
+        &self,
+        name: &dyn Display,
+        ctx: &EvalContext,
+        args: Vec<Operand>,
+    ) -> Result<Operand, Error> {
+        let current = thread::current().id();
+        let owner = || self.owner.lock().unwrap_or_else(PoisonError::into_inner);
+        if *owner() == Some(current) {
+            Err(Error::Custom(format!(
+                "{name} can not be called recursively"
+            )))?;
+        }
+        let mut handler = self.handler.lock().unwrap_or_else(PoisonError::into_inner);
+        *owner() = Some(current);
+        let _owner = OwnerGuard(&self.owner);
+        handler(ctx, args)
+    }
 }
 
-fn op_bit_xor_128(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u128;
-    let a = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()? as u128;
-    operands.push(Operand::Integer((a.bitxor(b)) as Integer));
+/// Clears the owner of a [`SharedHandler`] when the handler returns or panics
+struct OwnerGuard<'a>(&'a Mutex<Option<ThreadId>>);
 
-    Ok(())
-}
-
-fn ascii(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let operand = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    match operand {
-        Operand::String(s) => {
-            let int = s
-                .chars()
-                .map(|c| c as u8)
-                .fold(0, |pre, b| (pre << 8) + b as Integer);
-            operands.push(Operand::Integer(int));
-        }
-        Operand::Integer(mut int) => {
-            let mut chars = vec![];
-            while int != 0 {
-                chars.push((int & 0xFF) as u8 as char);
-                int >>= 8;
-            }
-            chars.reverse();
-            operands.push(Operand::String(String::from_iter(chars)))
-        }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::String, OperandType::Integer],
-            got: operand.data_type(),
-        })?,
+impl Drop for OwnerGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
     }
-    Ok(())
 }
 
-fn rev(operands: &mut Vec<Operand>) -> Result<(), Error> {
-    let operand = operands.pop().ok_or(Error::NotEnoughOperand)?;
-    match operand {
-        Operand::String(s) => {
-            let chars = s.chars().rev().collect::<Vec<_>>();
-            operands.push(Operand::String(String::from_iter(chars)))
-        }
-        Operand::Integer(mut int) => {
-            let mut reversed = 0;
-            while int != 0 {
-                reversed <<= 8;
-                reversed += int & 0xFF;
-                int >>= 8;
-            }
-            operands.push(Operand::Integer(reversed))
+/// Adapt a stack-style handler to a [`PureHandler`]
+pub fn stack_handler(action: OperatorAction) -> PureHandler {
+    Box::new(move |_, mut operands| {
+        action(&mut operands)?;
+        if operands.len() != 1 {
+            Err(Error::RedundantOperand(operands.len()))?;
         }
-        _ => Err(Error::InvalidDataType {
-            expected: vec![OperandType::String, OperandType::Integer],
-            got: operand.data_type(),
-        })?,
-    }
-
-    Ok(())
+        operands.pop().ok_or(Error::NotEnoughOperand)
+    })
 }
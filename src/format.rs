@@ -0,0 +1,419 @@
+use std::ops::RangeInclusive;
+
+use crate::{lex::check_radix, Error, Integer};
+
+/// Character inserted between digit groups.
+/// Only [`Separator::Underscore`] and [`Separator::Apostrophe`] are read back as a single literal,
+/// [`Separator::Space`] and [`Separator::Comma`] are for display only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// `_`, like rust literals
+    Underscore,
+    /// `'`, like c++ literals
+    Apostrophe,
+    /// ` `, read back as separate numbers
+    Space,
+    /// `,`, read back as separate arguments
+    Comma,
+}
+
+impl Separator {
+    pub fn as_char(&self) -> char {
+        match self {
+            Separator::Underscore => '_',
+            Separator::Apostrophe => '\'',
+            Separator::Space => ' ',
+            Separator::Comma => ',',
+        }
+    }
+}
+
+/// How negative numbers are represented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sign {
+    /// Leading `-` for negative numbers only
+    #[default]
+    Negative,
+    /// Leading `-` for negative numbers and `+` for the others
+    Always,
+    /// Two's complement in the given bit width, without sign.
+    /// Bits above the width are discarded
+    TwosComplement(u32),
+}
+
+/// Builder of integer formatting options
+///
+/// ```
+/// use rcal::format::{NumberFormat, Separator};
+///
+/// let format = NumberFormat::new(16)
+///     .prefix(true)
+///     .uppercase(true)
+///     .min_width(8)
+///     .group(4, Separator::Underscore);
+/// assert_eq!(format.format(0xF0), "0x0000_00F0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberFormat {
+    radix: u32,
+    prefix: bool,
+    uppercase: bool,
+    group: Option<(usize, Separator)>,
+    min_width: usize,
+    sign: Sign,
+}
+
+impl NumberFormat {
+    /// # Panics
+    /// Panics if the radix is not in the range of 2 to 36, see [`NumberFormat::try_new`]
+    pub fn new(radix: u32) -> Self {
+        Self::try_new(radix).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Format in the given radix, or [`Error::InvalidRadix`] if it is not in the range of 2 to 36
+    pub fn try_new(radix: u32) -> Result<Self, Error> {
+        Ok(Self {
+            radix: check_radix(radix as Integer)?,
+            prefix: false,
+            uppercase: false,
+            group: None,
+            min_width: 0,
+            sign: Sign::default(),
+        })
+    }
+
+    pub fn dec() -> Self {
+        Self::new(10)
+    }
+
+    pub fn hex() -> Self {
+        Self::new(16)
+    }
+
+    pub fn oct() -> Self {
+        Self::new(8)
+    }
+
+    pub fn bin() -> Self {
+        Self::new(2)
+    }
+
+    /// Add the literal prefix of the radix (`0x`, `0o`, `0b`, none for decimal and `N#` for the others)
+    pub fn prefix(mut self, prefix: bool) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Use upper case letters for digits above 9
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.uppercase = uppercase;
+        self
+    }
+
+    /// Insert the separator between every `size` digits, counting from the least significant one
+    pub fn group(mut self, size: usize, separator: Separator) -> Self {
+        self.group = (size > 0).then_some((size, separator));
+        self
+    }
+
+    /// Pad the digits with zeros to at least `width` digits.
+    /// The prefix, sign and separators are not counted
+    pub fn min_width(mut self, width: usize) -> Self {
+        self.min_width = width;
+        self
+    }
+
+    /// # Panics
+    /// Panics if the width of [`Sign::TwosComplement`] is not in the range of 1 to 128
+    pub fn sign(mut self, sign: Sign) -> Self {
+        if let Sign::TwosComplement(bits) = sign {
+            assert!(
+                (1..=Integer::BITS).contains(&bits),
+                "two's complement width must be in the range of 1 to 128, got {bits}"
+            );
+        }
+        self.sign = sign;
+        self
+    }
+
+    pub fn radix_prefix(&self) -> String {
+        match self.radix {
+            16 => "0x".to_string(),
+            8 => "0o".to_string(),
+            2 => "0b".to_string(),
+            10 => String::new(),
+            radix => format!("{radix}#"),
+        }
+    }
+
+    pub fn format(&self, num: Integer) -> String {
+        let (negative, mut magnitude) = match self.sign {
+            Sign::TwosComplement(bits) => (false, num as u128 & (u128::MAX >> (128 - bits))),
+            _ => (num < 0, num.unsigned_abs()),
+        };
+
+        let mut digits = vec![];
+        while magnitude != 0 || digits.is_empty() {
+            let digit = char::from_digit((magnitude % self.radix as u128) as u32, self.radix)
+                .expect("digit is always less than the radix");
+            digits.push(if self.uppercase {
+                digit.to_ascii_uppercase()
+            } else {
+                digit
+            });
+            magnitude /= self.radix as u128;
+        }
+        while digits.len() < self.min_width {
+            digits.push('0');
+        }
+
+        let mut result = String::new();
+        if negative {
+            result.push('-');
+        } else if self.sign == Sign::Always {
+            result.push('+');
+        }
+        if self.prefix {
+            result.push_str(&self.radix_prefix());
+        }
+        for (idx, digit) in digits.iter().enumerate().rev() {
+            result.push(*digit);
+            if let Some((size, separator)) = self.group {
+                if idx != 0 && idx % size == 0 {
+                    result.push(separator.as_char());
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::dec()
+    }
+}
+
+/// Smallest standard register width (8, 16, 32, 64 or 128 bits) that holds the value,
+/// either as signed or unsigned integer
+pub fn fit_width(num: Integer) -> u32 {
+    [8, 16, 32, 64]
+        .into_iter()
+        .find(|w| fits_width(num, *w))
+        .unwrap_or(Integer::BITS)
+}
+
+/// Whether the value fits into `width` bits, either as signed or unsigned integer
+pub fn fits_width(num: Integer, width: u32) -> bool {
+    width >= Integer::BITS || (num >= -(1 << (width - 1)) && num < (1 << width))
+}
+
+/// Verilog sized literal of the value in hexadecimal, like `12'hFFF`.
+/// Negative values are in two's complement, bits above the width are discarded
+pub fn verilog(num: Integer, width: u32, group: bool) -> String {
+    let format = NumberFormat::hex()
+        .uppercase(true)
+        .sign(Sign::TwosComplement(width))
+        .min_width(width.div_ceil(4) as usize);
+    let format = if group {
+        format.group(4, Separator::Underscore)
+    } else {
+        format
+    };
+    format!("{width}'h{}", format.format(num))
+}
+
+/// Ranges of consecutive set bits in the lowest `width` bits, from the least significant one
+pub fn set_bit_ranges(num: Integer, width: u32) -> Vec<RangeInclusive<u32>> {
+    let mut ranges: Vec<RangeInclusive<u32>> = vec![];
+    for i in (0..width).filter(|i| num & (1 << i) != 0) {
+        match ranges.last_mut() {
+            Some(last) if *last.end() + 1 == i => *last = *last.start()..=i,
+            _ => ranges.push(i..=i),
+        }
+    }
+    ranges
+}
+
+/// Draw the lowest `width` bits as a ruler of bit indices over 0/1 cells,
+/// 32 bits per row with separators at nibble and byte boundaries.
+/// Set bits are highlighted with ANSI escape codes if `color` is true
+pub fn bit_ruler(num: Integer, width: u32, color: bool) -> String {
+    let mut result = String::new();
+    for row in (0..width.div_ceil(32)).rev() {
+        let low = row * 32;
+        let high = (low + 32).min(width) - 1;
+        let mut indices = String::new();
+        let mut cells = String::new();
+        for i in (low..=high).rev() {
+            indices.push_str(&format!("{i:>3}"));
+            if num & (1 << i) != 0 && color {
+                cells.push_str("\x1b[1;32m  1\x1b[0m");
+            } else {
+                cells.push_str(&format!("{:>3}", (num >> i) & 1));
+            }
+            let separator = if i == low {
+                ""
+            } else if i % 8 == 0 {
+                " |"
+            } else if i % 4 == 0 {
+                " "
+            } else {
+                ""
+            };
+            indices.push_str(separator);
+            cells.push_str(separator);
+        }
+        if row != width.div_ceil(32) - 1 {
+            result.push('\n');
+        }
+        result.push_str(&indices);
+        result.push('\n');
+        result.push_str(&cells);
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lex::{tokenize, IntegerLiteral, LexToken};
+
+    #[test]
+    fn test_format() {
+        assert_eq!(NumberFormat::dec().format(-1234), "-1234");
+        assert_eq!(
+            NumberFormat::dec()
+                .group(3, Separator::Comma)
+                .format(-1234567),
+            "-1,234,567"
+        );
+        assert_eq!(
+            NumberFormat::dec().group(3, Separator::Comma).format(-123),
+            "-123"
+        );
+        assert_eq!(NumberFormat::dec().sign(Sign::Always).format(5), "+5");
+        assert_eq!(
+            NumberFormat::hex()
+                .sign(Sign::TwosComplement(16))
+                .uppercase(true)
+                .format(-2),
+            "FFFE"
+        );
+        assert_eq!(
+            NumberFormat::bin()
+                .prefix(true)
+                .group(4, Separator::Space)
+                .format(0b100101),
+            "0b10 0101"
+        );
+        assert_eq!(
+            NumberFormat::oct()
+                .min_width(6)
+                .group(3, Separator::Apostrophe)
+                .format(0o17),
+            "000'017"
+        );
+        assert_eq!(NumberFormat::new(36).uppercase(true).format(35), "Z");
+        assert_eq!(NumberFormat::try_new(37), Err(Error::InvalidRadix(37)));
+        assert_eq!(NumberFormat::try_new(1), Err(Error::InvalidRadix(1)));
+        assert_eq!(NumberFormat::hex().format(0), "0");
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(fit_width(0xFF), 8);
+        assert_eq!(fit_width(-129), 16);
+        assert_eq!(fit_width(0x1_0000_0000), 64);
+        assert!(fits_width(-2048, 12) && fits_width(4095, 12) && !fits_width(4096, 12));
+        assert_eq!(verilog(0xDEADBEEF, 32, true), "32'hDEAD_BEEF");
+        assert_eq!(verilog(-1, 12, false), "12'hFFF");
+        assert_eq!(verilog(5, 1, false), "1'h1");
+        assert_eq!(set_bit_ranges(0x10F0, 16), vec![4..=7, 12..=12]);
+        assert_eq!(set_bit_ranges(-1, 8), vec![0..=7]);
+        assert_eq!(
+            bit_ruler(0x1A, 8, false),
+            "  7  6  5  4   3  2  1  0\n  0  0  0  1   1  0  1  0\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let values = [
+            0,
+            1,
+            7,
+            255,
+            0x1234_5678,
+            -42,
+            Integer::MAX,
+            Integer::MIN + 1,
+        ];
+        let groups = [
+            None,
+            Some((3, Separator::Underscore)),
+            Some((4, Separator::Apostrophe)),
+            Some((3, Separator::Space)),
+            Some((4, Separator::Comma)),
+        ];
+        for radix in 2..=36 {
+            for uppercase in [false, true] {
+                for min_width in [0, 8, 40] {
+                    for group in groups {
+                        let mut format = NumberFormat::new(radix)
+                            .prefix(true)
+                            .uppercase(uppercase)
+                            .min_width(min_width);
+                        if let Some((size, separator)) = group {
+                            format = format.group(size, separator);
+                        }
+                        for value in values {
+                            let formatted = format.format(value);
+                            let tokens = tokenize(&formatted);
+                            let literal = IntegerLiteral::new(value.abs(), radix);
+                            let expected = if value < 0 {
+                                vec![LexToken::Minus(None), LexToken::Integer(literal)]
+                            } else {
+                                vec![LexToken::Integer(literal)]
+                            };
+                            // display-only separators split the literal
+                            if formatted.contains([' ', ',']) {
+                                assert_ne!(tokens.ok(), Some(expected), "{formatted}");
+                            } else {
+                                assert_eq!(tokens.unwrap(), expected, "{formatted}");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // two's complement reads back as the unsigned value of its bits,
+        // below 128 bits where it is positive as a literal
+        for bits in [1, 12, 64, 127] {
+            for radix in [2, 8, 16] {
+                let format = NumberFormat::new(radix)
+                    .prefix(true)
+                    .group(4, Separator::Underscore)
+                    .sign(Sign::TwosComplement(bits));
+                for value in values {
+                    let formatted = format.format(value);
+                    let unsigned = value as u128 & (u128::MAX >> (128 - bits));
+                    let literal = IntegerLiteral::new(unsigned as Integer, radix);
+                    assert_eq!(
+                        tokenize(&formatted).unwrap(),
+                        vec![LexToken::Integer(literal)],
+                        "{formatted}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "two's complement width")]
+    fn test_twos_complement_width() {
+        NumberFormat::hex().sign(Sign::TwosComplement(0));
+    }
+}
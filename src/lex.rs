@@ -1,55 +1,210 @@
 use logos::{Lexer, Logos};
 
-use std::str::FromStr as _;
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Display, ops::Range, str::FromStr as _};
 
-use crate::{Error, Float, Integer};
+use crate::{
+    expr::operator::{braced_hint, width_hint},
+    format::{NumberFormat, Sign},
+    Error, Float, Integer,
+};
+
+/// Integer literal with the radix it is written in
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct IntegerLiteral {
+    pub value: Integer,
+    pub radix: u32,
+    /// Bit width of sized literals, like `8'hFF`
+    pub width: Option<usize>,
+    /// Whether the value of the sized literal did not fit into its width and is truncated
+    pub truncated: bool,
+    /// C `u` suffix
+    pub unsigned: bool,
+    /// C `l` or `ll` suffix
+    pub long: bool,
+}
+
+impl IntegerLiteral {
+    pub fn new(value: Integer, radix: u32) -> Self {
+        Self {
+            value,
+            radix,
+            width: None,
+            truncated: false,
+            unsigned: false,
+            long: false,
+        }
+    }
+}
+
+/// The literal in its radix, with its size or C suffixes, like `8'hFF` and `0x10ul`
+impl Display for IntegerLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(width) = self.width {
+            let base = match self.radix {
+                16 => 'h',
+                8 => 'o',
+                2 => 'b',
+                _ => 'd',
+            };
+            let format = NumberFormat::new(if base == 'd' { 10 } else { self.radix })
+                .uppercase(true)
+                .sign(Sign::TwosComplement(width as u32));
+            return write!(f, "{width}'{base}{}", format.format(self.value));
+        }
+        let format = NumberFormat::new(self.radix).prefix(true).uppercase(true);
+        write!(f, "{}", format.format(self.value))?;
+        if self.unsigned {
+            write!(f, "u")?;
+        }
+        if self.long {
+            write!(f, "l")?;
+        }
+        Ok(())
+    }
+}
+
+/// Operator syntax of expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Rcal,
+    /// C operators, precedence and integer types: `^` is the XOR of the promoted operands,
+    /// `!` the logical NOT, and the `u` and `l` suffixes are never bit width hints
+    C,
+}
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 #[logos(skip r"[ \t\n\f]+")]
 #[logos(error=Error)]
+#[logos(extras = Dialect)]
 pub enum LexToken {
-    #[regex(r"(?i)\d+[kmgtp]?", dec_number, priority = 3)]
-    #[regex(r"0x[\da-fA-F]+", hex_number)]
-    #[regex(r"0o[0-7]+", oct_number)]
-    #[regex(r"0b[01]+", bin_number)]
-    Integer(Integer),
+    /// Digits can be separated by `_` or `'`, like `0xFFFF_0000` and `1'000'000`.
+    /// Integers with radix prefix and decimal integers accept C suffixes (`u`, `l`, `ul`, `ll`, `ull`...).
+    /// A `b` or `l` suffix directly followed by an operator taking a bit width hint is rejected,
+    /// since it can also be a bit width hint, except in the C dialect.
+    /// Hexadecimal integers with `h` suffix, like `0FFh`, are merged from several tokens by [`tokenize_with`]
+    #[regex(
+        r"\d+(?:['_]\d+)*(?:[kmgtpKMGTP]|[uU](?:ll|LL|[lL])?|(?:ll|LL|[lL])[uU]?)?",
+        integer,
+        priority = 3
+    )]
+    #[regex(r"\d+(?:['_]\d+)*\.\d+[kmgtpKMGTP]", integer)]
+    #[regex(
+        r"0[xX][\da-fA-F]+(?:['_][\da-fA-F]+)*(?:[uU](?:ll|LL|[lL])?|(?:ll|LL|[lL])[uU]?)?",
+        integer
+    )]
+    #[regex(
+        r"0[oO][0-7]+(?:['_][0-7]+)*(?:[uU](?:ll|LL|[lL])?|(?:ll|LL|[lL])[uU]?)?",
+        integer
+    )]
+    #[regex(
+        r"0[bB][01]+(?:['_][01]+)*(?:[uU](?:ll|LL|[lL])?|(?:ll|LL|[lL])[uU]?)?",
+        integer
+    )]
+    #[regex(r"[01]+(?:['_][01]+)*[bB]", integer)]
+    #[regex(r"\d+#[0-9a-zA-Z]+(?:['_][0-9a-zA-Z]+)*", integer)]
+    /// Verilog sized literals, like `32'hDEAD_BEEF`, `8'b1010_0101`, `12'd4095` and `8'sh80`.
+    /// Values wider than the size are truncated
+    #[regex(
+        r"\d+'[sS]?(?:[hH][\da-fA-F][\da-fA-F_]*|[dD]\d[\d_]*|[oO][0-7][0-7_]*|[bB][01][01_]*)",
+        sized
+    )]
+    Integer(IntegerLiteral),
+    /// Verilog fill literals `'0` and `'1`, all bits of the width of the other operand.
+    /// Only produced by [`tokenize_with`]
+    Fill(bool),
     #[regex(
         r"(?:[1-9]\d*|\.\d+|\d+\.\d+)(?:[eE][-+]?(?:\d+|\.\d+|\d+\.\d+))?",
         science_notation,
         priority = 2
     )]
     Float(Float),
-    #[token("+")]
-    Plus,
-    #[token("-")]
-    Minus,
-    #[token("*")]
-    Mul,
+    /// Bit width hint of arithmetic, like `w+`, `None` for arithmetic on the full integer
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})\+", bit_width)]
+    #[token("+", no_width)]
+    Plus(Option<usize>),
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})-", bit_width)]
+    #[token("-", no_width)]
+    Minus(Option<usize>),
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})\*", bit_width)]
+    #[token("*", no_width)]
+    #[token("times", no_width)]
+    Mul(Option<usize>),
     #[token("/")]
     Div,
     #[token("(")]
     OpenParenthesis,
     #[token(")")]
     CloseParenthesis,
+    /// `[`, alternate grouping which needs no quoting in shells
+    #[token("[")]
+    OpenBracket,
+    #[token("]")]
+    CloseBracket,
     #[token("|")]
+    #[token("or")]
     BitOr,
     #[token("&")]
+    #[token("and")]
     BitAnd,
-    #[regex(r"(b|w|dw|l|ll)?\^", bit_width)]
-    BitXor(usize),
-    #[regex(r"(b|w|dw|l)?!", bit_width)]
-    #[regex(r"(b|w|dw|l)?~", bit_width)]
-    BitNot(usize),
+    /// Bit width hint, `None` for the default width of the evaluator.
+    /// Besides `b`, `w`, `dw`, `l` and `ll`, any width from 1 to 128 is written as `u24` or `{24}`
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})?\^", bit_width)]
+    #[token("xor", no_width)]
+    BitXor(Option<usize>),
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})?!", bit_width)]
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})?~", bit_width)]
+    #[token("not", no_width)]
+    BitNot(Option<usize>),
+    /// `!` of the C dialect, only produced by [`tokenize_with`]
+    LogicalNot,
+    /// Comparisons, logical and conditional operators, only in the C dialect
+    #[token("<", c_only)]
+    Less,
+    #[token("<=", c_only)]
+    LessEqual,
+    #[token(">", c_only)]
+    Greater,
+    #[token(">=", c_only)]
+    GreaterEqual,
+    #[token("==", c_only)]
+    Equal,
+    #[token("!=", c_only)]
+    NotEqual,
+    #[token("&&", c_only)]
+    LogicalAnd,
+    #[token("||", c_only)]
+    LogicalOr,
+    #[token("?", c_only)]
+    Question,
+    #[token(":", c_only)]
+    Colon,
     #[token("^^")]
+    #[token("pow")]
     Expo,
     #[token("%")]
+    #[token("mod")]
     Mod,
     #[regex("[a-zA-Z]\\w*", store_identifier)]
     Custom(String),
-    #[token(">>")]
-    RightShift,
-    #[token("<<")]
-    LeftShift,
+    /// Bit width hint of shifts, like `dw<<`, `None` for shifts of the full integer
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})>>", bit_width)]
+    #[token(">>", no_width)]
+    #[token("shr", no_width)]
+    RightShift(Option<usize>),
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})<<", bit_width)]
+    #[token("<<", no_width)]
+    #[token("shl", no_width)]
+    LeftShift(Option<usize>),
+    /// Logical right shift, zeros are shifted in
+    #[regex(r"(?:b|w|dw|l|ll|u\d+|\{\d+\})>>>", bit_width)]
+    #[token(">>>", no_width)]
+    LogicalShift(Option<usize>),
+    /// Bit width hint of rotates, like `{12}rol`, `None` for the default width of the evaluator
+    #[regex(r"(?:\{\d+\})?rol", bit_width)]
+    RotateLeft(Option<usize>),
+    #[regex(r"(?:\{\d+\})?ror", bit_width)]
+    RotateRight(Option<usize>),
     #[token(",")]
     Comma,
     #[regex(r#""([^"]|\\")*""#, store_string)]
@@ -57,97 +212,685 @@ pub enum LexToken {
     String(String),
     #[regex(r"(?i)bit\d+", bit_number)]
     Bit(Integer),
+    /// Symbol of a registered infix operator, only produced by [`tokenize_with`]
+    Infix(String),
 }
 
-impl LexToken {
-    /// lower is higher
-    pub fn precedence(&self) -> Option<usize> {
+/// The token as written in a formula, keywords like `xor` are written as their symbol
+impl Display for LexToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Expo | Self::BitNot(_) => Some(0),
-            Self::Mul
-            | Self::Mod
-            | Self::Div
-            | Self::BitAnd
-            | Self::BitXor(_)
-            | Self::LeftShift
-            | Self::RightShift => Some(1),
-            Self::Plus | Self::Minus | Self::BitOr => Some(2),
-            _ => None,
+            LexToken::Integer(literal) => write!(f, "{literal}"),
+            LexToken::Fill(bit) => write!(f, "'{}", *bit as u8),
+            LexToken::Float(float) => write!(f, "{float:?}"),
+            LexToken::Plus(width) => write!(f, "{}+", width_hint(*width)),
+            LexToken::Minus(width) => write!(f, "{}-", width_hint(*width)),
+            LexToken::Mul(width) => write!(f, "{}*", width_hint(*width)),
+            LexToken::Div => write!(f, "/"),
+            LexToken::OpenParenthesis => write!(f, "("),
+            LexToken::CloseParenthesis => write!(f, ")"),
+            LexToken::OpenBracket => write!(f, "["),
+            LexToken::CloseBracket => write!(f, "]"),
+            LexToken::BitOr => write!(f, "|"),
+            LexToken::BitAnd => write!(f, "&"),
+            LexToken::BitXor(width) => write!(f, "{}^", width_hint(*width)),
+            LexToken::BitNot(width) => write!(f, "{}~", width_hint(*width)),
+            LexToken::LogicalNot => write!(f, "!"),
+            LexToken::Less => write!(f, "<"),
+            LexToken::LessEqual => write!(f, "<="),
+            LexToken::Greater => write!(f, ">"),
+            LexToken::GreaterEqual => write!(f, ">="),
+            LexToken::Equal => write!(f, "=="),
+            LexToken::NotEqual => write!(f, "!="),
+            LexToken::LogicalAnd => write!(f, "&&"),
+            LexToken::LogicalOr => write!(f, "||"),
+            LexToken::Question => write!(f, "?"),
+            LexToken::Colon => write!(f, ":"),
+            LexToken::Expo => write!(f, "^^"),
+            LexToken::Mod => write!(f, "%"),
+            LexToken::Custom(name) => write!(f, "{name}"),
+            LexToken::RightShift(width) => write!(f, "{}>>", width_hint(*width)),
+            LexToken::LeftShift(width) => write!(f, "{}<<", width_hint(*width)),
+            LexToken::LogicalShift(width) => write!(f, "{}>>>", width_hint(*width)),
+            LexToken::RotateLeft(width) => write!(f, "{}rol", braced_hint(*width)),
+            LexToken::RotateRight(width) => write!(f, "{}ror", braced_hint(*width)),
+            LexToken::Comma => write!(f, ","),
+            LexToken::String(s) => write!(f, "\"{s}\""),
+            LexToken::Bit(n) => write!(f, "bit{n}"),
+            LexToken::Infix(symbol) => write!(f, "{symbol}"),
+        }
+    }
+}
+
+/// Options of [`tokenize_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexOptions<'a> {
+    /// Symbols of registered infix operators, lexed as [`LexToken::Infix`]
+    pub symbols: &'a [&'a str],
+    /// Radix of numbers without prefix. Digits above 9 are upper case letters, like bc
+    pub ibase: u32,
+    /// Give hexadecimal and binary integers the width of their digits, leading zeros included,
+    /// like sized literals. `0x00FF` is 16 bits wide
+    pub infer_width: bool,
+    /// In the C dialect, `!` is [`LexToken::LogicalNot`], integers with a leading zero are octal,
+    /// character literals like `'A'` are integers and comparisons like `<=` are tokens
+    pub dialect: Dialect,
+    /// Split or reject the adjacent tokens which can also be read as an implicit multiplication,
+    /// like `3pi` and `4kx`
+    pub implicit_mul: bool,
+    /// Names of variables and functions, a word directly after a literal suffix is split from it
+    /// with implicit multiplication if it is one of them
+    pub names: &'a [&'a str],
+}
+
+impl Default for LexOptions<'_> {
+    fn default() -> Self {
+        Self {
+            symbols: &[],
+            ibase: 10,
+            infer_width: false,
+            dialect: Dialect::Rcal,
+            implicit_mul: false,
+            names: &[],
         }
     }
 }
 
 pub fn tokenize(formula: &str) -> Result<Vec<LexToken>, Error> {
-    let lexer = LexToken::lexer(formula);
-    Ok(lexer
-        .collect::<Result<Vec<_>, Error>>()?
+    tokenize_with(formula, &LexOptions::default())
+}
+
+/// Tokenize with the options.
+/// At each position, the longer of the matching symbol and the built-in token is taken
+pub fn tokenize_with(formula: &str, options: &LexOptions) -> Result<Vec<LexToken>, Error> {
+    Ok(tokenize_spanned(formula, options)?
         .into_iter()
+        .map(|(token, _)| token)
         .collect())
 }
 
-fn dec_number(lex: &mut Lexer<LexToken>) -> Result<Integer, Error> {
-    let mut token = lex.slice();
-    let unit = if let Some(remain) = token.strip_suffix(['k', 'K']) {
-        token = remain;
-        1024
-    } else if let Some(remain) = token.strip_suffix(['m', 'M']) {
-        token = remain;
-        1024 * 1024
-    } else if let Some(remain) = token.strip_suffix(['g', 'G']) {
-        token = remain;
-        1024 * 1024 * 1024
-    } else if let Some(remain) = token.strip_suffix(['t', 'T']) {
-        token = remain;
-        1024 * 1024 * 1024 * 1024
-    } else if let Some(remain) = token.strip_suffix(['p', 'P']) {
-        token = remain;
-        1024 * 1024 * 1024 * 1024 * 1024
-    } else {
-        1
+/// Tokenize with the options, with the byte range of each token in the formula
+pub fn tokenize_spanned(
+    formula: &str,
+    options: &LexOptions,
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    let tokens = lex_spanned(formula, options.symbols, options.dialect)?;
+    let mut tokens = merge_hex_suffix(formula, tokens)?;
+    if options.ibase != 10 {
+        tokens = rebase(formula, tokens, options.ibase)?;
+    }
+    if options.dialect == Dialect::C {
+        tokens = c_tokens(formula, tokens)?;
+    }
+    let mut tokens = split_letter_hints(formula, tokens);
+    if options.implicit_mul {
+        tokens = juxtapose(formula, tokens, options.names)?;
+    }
+    if options.infer_width {
+        for (token, span) in &mut tokens {
+            if let LexToken::Integer(literal @ IntegerLiteral { width: None, .. }) = token {
+                literal.width = natural_width(&formula[span.clone()], literal.radix);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Split binary operators with a letter bit width hint in operand position into an identifier and the operator,
+/// so `b+1` and `(l-1)` are operations on the variables `b` and `l`.
+/// The `uN` hint of an operator after `as` is the type of the cast, so `x as u8+1` is `(x as u8) + 1`
+fn split_letter_hints(
+    formula: &str,
+    tokens: Vec<(LexToken, Range<usize>)>,
+) -> Vec<(LexToken, Range<usize>)> {
+    let mut result: Vec<(LexToken, Range<usize>)> = vec![];
+    for (token, span) in tokens {
+        let hinted = matches!(
+            token,
+            LexToken::Plus(Some(_))
+                | LexToken::Minus(Some(_))
+                | LexToken::Mul(Some(_))
+                | LexToken::BitXor(Some(_))
+                | LexToken::LeftShift(Some(_))
+                | LexToken::RightShift(Some(_))
+                | LexToken::LogicalShift(Some(_))
+        );
+        let operand_position = !matches!(
+            result.last(),
+            Some((
+                LexToken::Integer(_)
+                    | LexToken::Float(_)
+                    | LexToken::String(_)
+                    | LexToken::Bit(_)
+                    | LexToken::Fill(_)
+                    | LexToken::Custom(_)
+                    | LexToken::CloseParenthesis
+                    | LexToken::CloseBracket,
+                _
+            ))
+        );
+        let cast = matches!(result.last(), Some((LexToken::Custom(keyword), _)) if keyword == "as");
+        let slice = &formula[span.clone()];
+        let hint_len = slice.len()
+            - match slice.strip_prefix('u') {
+                Some(width) if cast => width.trim_start_matches(|c: char| c.is_ascii_digit()),
+                _ => slice.trim_start_matches(['b', 'w', 'd', 'l']),
+            }
+            .len();
+        let operator = span.start + hint_len..span.end;
+        match LexToken::lexer(&formula[operator.clone()]).next() {
+            Some(Ok(op)) if hinted && (operand_position || cast) && hint_len > 0 => {
+                let hint = &formula[span.start..operator.start];
+                result.push((
+                    LexToken::Custom(hint.to_string()),
+                    span.start..operator.start,
+                ));
+                result.push((op, operator));
+            }
+            _ => result.push((token, span)),
+        }
+    }
+    result
+}
+
+/// Split or reject the adjacent tokens whose reading changes with implicit multiplication.
+/// A literal suffix followed by a word, like `3pi` lexed as `3p` and `i`, is split into the number and the word
+/// if the word is a name of `names` or a bit shortcut, like `1bit3`, and the rest of the word is not.
+/// Otherwise it is ambiguous, like `4kx`, and so are numbers like `1.5.5`
+/// and letter bit width hints after an operand, like `2w+1`
+fn juxtapose(
+    formula: &str,
+    tokens: Vec<(LexToken, Range<usize>)>,
+    names: &[&str],
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    let number = |token: &LexToken| matches!(token, LexToken::Integer(_) | LexToken::Float(_));
+    let known = |token: &LexToken| match token {
+        LexToken::Custom(name) => names.contains(&name.as_str()),
+        token => matches!(token, LexToken::Bit(_)),
+    };
+    let single = |span: Range<usize>| {
+        let mut lexer = LexToken::lexer(&formula[span]);
+        match (lexer.next(), lexer.next()) {
+            (Some(Ok(token)), None) => Some(token),
+            _ => None,
+        }
+    };
+    let mut result: Vec<(LexToken, Range<usize>)> = vec![];
+    for (token, span) in tokens {
+        let Some((last, last_span)) = result.last().filter(|(_, last)| last.end == span.start)
+        else {
+            result.push((token, span));
+            continue;
+        };
+        let (last_slice, slice) = (&formula[last_span.clone()], &formula[span.clone()]);
+        let word = slice.starts_with(|c: char| c.is_ascii_alphanumeric());
+        let hinted = matches!(
+            token,
+            LexToken::Plus(Some(_))
+                | LexToken::Minus(Some(_))
+                | LexToken::Mul(Some(_))
+                | LexToken::BitXor(Some(_))
+                | LexToken::BitNot(Some(_))
+                | LexToken::LeftShift(Some(_))
+                | LexToken::RightShift(Some(_))
+                | LexToken::LogicalShift(Some(_))
+        ) && slice.starts_with(['b', 'w', 'd', 'l']);
+        let operand = number(last)
+            || matches!(
+                last,
+                LexToken::Bit(_) | LexToken::CloseParenthesis | LexToken::CloseBracket
+            );
+        let suffix = last_slice.len()
+            - last_slice
+                .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                .len();
+        let prefixed = last_slice.contains('#')
+            || ["0x", "0o", "0b"]
+                .iter()
+                .any(|prefix| last_slice.to_ascii_lowercase().starts_with(prefix));
+        let ambiguous_span = last_span.start..span.end;
+        if number(last) && word && suffix > 0 && !prefixed {
+            let digits = last_span.start..last_span.end - suffix;
+            let joined = digits.end..span.end;
+            if let (Some(value), Some(word)) = (single(digits.clone()), single(joined.clone())) {
+                if number(&value) && known(&word) && !known(&token) {
+                    result.pop();
+                    result.push((value, digits));
+                    result.push((word, joined));
+                    continue;
+                }
+            }
+        }
+        if (number(last) && (number(&token) || word && last_slice.ends_with(char::is_alphabetic)))
+            || (operand && hinted)
+        {
+            Err(Error::Spanned {
+                error: Box::new(Error::AmbiguousJuxtaposition(
+                    formula[ambiguous_span.clone()].to_string(),
+                )),
+                span: ambiguous_span,
+            })?;
+        }
+        result.push((token, span));
+    }
+    Ok(result)
+}
+
+/// Width of the digits of a hexadecimal or binary integer literal, leading zeros included
+fn natural_width(slice: &str, radix: u32) -> Option<usize> {
+    let (prefix, suffix, bits) = match radix {
+        16 => ("0x", 'h', 4),
+        2 => ("0b", 'b', 1),
+        _ => return None,
+    };
+    let lower = slice.to_ascii_lowercase();
+    let digits = match lower
+        .strip_prefix(prefix)
+        .or_else(|| lower.split_once('#').map(|(_, digits)| digits))
+    {
+        Some(digits) => digits.trim_end_matches(['u', 'l']),
+        None => lower.strip_suffix(suffix).unwrap_or(&lower),
+    };
+    let width = digits.chars().filter(char::is_ascii_alphanumeric).count() * bits;
+    (1..=Integer::BITS as usize)
+        .contains(&width)
+        .then_some(width)
+}
+
+fn lex_spanned(
+    formula: &str,
+    symbols: &[&str],
+    dialect: Dialect,
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    let mut tokens = vec![];
+    let mut offset = 0;
+    loop {
+        let rest = &formula[offset..];
+        let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\x0c']);
+        offset += rest.len() - trimmed.len();
+        if trimmed.is_empty() {
+            return Ok(tokens);
+        }
+        let symbol = symbols
+            .iter()
+            .filter(|symbol| trimmed.starts_with(**symbol))
+            .max_by_key(|symbol| symbol.len());
+        if let Some(bit) = fill_literal(trimmed) {
+            tokens.push((LexToken::Fill(bit), offset..offset + 2));
+            offset += 2;
+            continue;
+        }
+        let mut lexer = LexToken::lexer_with_extras(trimmed, dialect);
+        let token = lexer.next();
+        let span = offset + lexer.span().start..offset + lexer.span().end;
+        match (symbol, token) {
+            (Some(symbol), Some(token)) if token.is_err() || symbol.len() >= span.len() => {
+                tokens.push((
+                    LexToken::Infix(symbol.to_string()),
+                    offset..offset + symbol.len(),
+                ));
+                offset += symbol.len();
+            }
+            (_, Some(token)) => {
+                let token = token.map_err(|error| Error::Spanned {
+                    error: Box::new(error),
+                    span: span.clone(),
+                })?;
+                offset = span.end;
+                tokens.push((token, span));
+            }
+            (_, None) => return Ok(tokens),
+        }
+    }
+}
+
+/// Fill literal at the start of the input. `'0` and `'1` followed by a word character or a quote are strings,
+/// so `'1 & 8'hF0` is not the string `1 & 8`
+fn fill_literal(input: &str) -> Option<bool> {
+    let bit = match input.get(..2)? {
+        "'0" => false,
+        "'1" => true,
+        _ => return None,
+    };
+    match input[2..].chars().next() {
+        Some(c) if c.is_alphanumeric() || c == '_' || c == '\'' => None,
+        _ => Some(bit),
+    }
+}
+
+/// Tokens of the C dialect: `!` is the logical NOT, decimal integers with a leading zero are octal
+/// and strings of a single character in single quotes are the integer of the character
+fn c_tokens(
+    formula: &str,
+    tokens: Vec<(LexToken, Range<usize>)>,
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    tokens
+        .into_iter()
+        .map(|(token, span)| {
+            let slice = &formula[span.clone()];
+            let token = match token {
+                LexToken::BitNot(None) if slice == "!" => LexToken::LogicalNot,
+                LexToken::Integer(literal @ IntegerLiteral { radix: 10, .. }) => {
+                    let digits = slice.trim_end_matches(['u', 'U', 'l', 'L']);
+                    let octal = digits.len() > 1
+                        && digits.starts_with('0')
+                        && digits
+                            .chars()
+                            .all(|c| c.is_ascii_digit() || c == '_' || c == '\'');
+                    if !octal {
+                        return Ok((LexToken::Integer(literal), span));
+                    }
+                    let value = parse_digits(digits, 8).map_err(|error| Error::Spanned {
+                        error: Box::new(error),
+                        span: span.clone(),
+                    })?;
+                    LexToken::Integer(IntegerLiteral {
+                        value: value.value,
+                        radix: 8,
+                        ..literal
+                    })
+                }
+                LexToken::String(s) if slice.starts_with('\'') && s.chars().count() == 1 => {
+                    let c = s.chars().next().unwrap_or_default();
+                    LexToken::Integer(IntegerLiteral::new(c as Integer, 10))
+                }
+                token => token,
+            };
+            Ok((token, span))
+        })
+        .collect()
+}
+
+/// Merge the adjacent tokens of hexadecimal integers with `h` suffix, like `0FFh`.
+/// They start with a decimal digit, so the letters are lexed as identifiers
+fn merge_hex_suffix(
+    formula: &str,
+    tokens: Vec<(LexToken, Range<usize>)>,
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    let mut result = vec![];
+    let mut idx = 0;
+    while let Some((token, span)) = tokens.get(idx) {
+        let merged = formula[span.clone()]
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| hex_suffix_run(formula, &tokens[idx..]))
+            .flatten();
+        match merged {
+            Some((count, run)) => {
+                let digits = &formula[run.start..run.end - 1];
+                let literal = parse_digits(digits, 16).map_err(|error| Error::Spanned {
+                    error: Box::new(error),
+                    span: run.clone(),
+                })?;
+                result.push((LexToken::Integer(literal), run));
+                idx += count;
+            }
+            None => {
+                result.push((token.clone(), span.clone()));
+                idx += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Number of adjacent tokens and their span forming a hexadecimal integer with `h` suffix
+fn hex_suffix_run(
+    formula: &str,
+    tokens: &[(LexToken, Range<usize>)],
+) -> Option<(usize, Range<usize>)> {
+    let mut run = tokens.first()?.1.clone();
+    for (count, (_, next)) in tokens.iter().enumerate().skip(1) {
+        if next.start != run.end {
+            break;
+        }
+        run.end = next.end;
+        let text = &formula[run.clone()];
+        if !text[..text.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == '_')
+        {
+            break;
+        }
+        if text.ends_with(['h', 'H']) {
+            return Some((count + 1, run));
+        }
+    }
+    None
+}
+
+/// Read the runs of adjacent tokens made of digits and upper case letters as numbers in `ibase`,
+/// so `1F` and `DEADBEEF` are numbers in radix 16
+fn rebase(
+    formula: &str,
+    tokens: Vec<(LexToken, Range<usize>)>,
+    ibase: u32,
+) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+    let is_digits = |span: &Range<usize>| {
+        formula[span.clone()]
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+    };
+    let mut result = vec![];
+    let mut idx = 0;
+    while let Some((token, span)) = tokens.get(idx) {
+        idx += 1;
+        let rebasable = matches!(
+            token,
+            LexToken::Integer(IntegerLiteral { radix: 10, .. })
+                | LexToken::Float(_)
+                | LexToken::Custom(_)
+        ) && is_digits(span);
+        if !rebasable {
+            result.push((token.clone(), span.clone()));
+            continue;
+        }
+        let mut end = idx;
+        let mut run = span.clone();
+        while let Some((_, next)) = tokens.get(end).filter(|(_, next)| next.start == run.end) {
+            if !is_digits(next) {
+                break;
+            }
+            run.end = next.end;
+            end += 1;
+        }
+        let digits = &formula[run.clone()];
+        if digits
+            .chars()
+            .all(|c| c.is_digit(ibase) || c.is_ascii_digit())
+        {
+            let value = Integer::from_str_radix(digits, ibase).map_err(|error| Error::Spanned {
+                error: Box::new(error.into()),
+                span: run.clone(),
+            })?;
+            result.push((LexToken::Integer(IntegerLiteral::new(value, ibase)), run));
+            idx = end;
+        } else {
+            result.push((token.clone(), span.clone()));
+        }
+    }
+    Ok(result)
+}
+
+/// Radix most integer literals are written in, `None` if there is no integer literal.
+/// Decimal literals only count if all the literals are decimal,
+/// since they are usually counts like shift amounts.
+/// Ties are broken in favor of hexadecimal, then binary, octal and the other radixes from the lowest
+pub fn dominant_radix(tokens: &[LexToken]) -> Option<u32> {
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for token in tokens {
+        if let LexToken::Integer(literal) = token {
+            *counts.entry(literal.radix).or_default() += 1;
+        }
+    }
+    if counts.len() > 1 {
+        counts.remove(&10);
+    }
+    let priority = |radix: u32| match radix {
+        16 => 0,
+        2 => 1,
+        8 => 2,
+        radix => 3 + radix,
+    };
+    counts
+        .into_iter()
+        .min_by_key(|(radix, count)| (Reverse(*count), priority(*radix)))
+        .map(|(radix, _)| radix)
+}
+
+fn integer(lex: &mut Lexer<LexToken>) -> Result<IntegerLiteral, Error> {
+    let slice = lex.slice();
+    let mut literal = parse_integer(slice)?;
+    if !slice.contains('#') {
+        let lower = slice.to_ascii_lowercase();
+        let suffix = &lower[lower.trim_end_matches(['u', 'l']).len()..];
+        literal.unsigned = suffix.contains('u');
+        literal.long = suffix.contains('l');
+    }
+    let hint_suffix = slice.ends_with('l') || (literal.radix == 2 && slice.ends_with('b'));
+    if lex.extras != Dialect::C
+        && hint_suffix
+        && lex
+            .remainder()
+            .starts_with(['^', '~', '!', '+', '-', '*', '<', '>'])
+    {
+        Err(Error::AmbiguousLiteral(slice.to_string()))?;
+    }
+    Ok(literal)
+}
+
+fn sized(lex: &mut Lexer<LexToken>) -> Result<IntegerLiteral, Error> {
+    let (width, digits) = lex.slice().split_once('\'').ok_or(Error::InvalidToken)?;
+    let width = match width.parse() {
+        Ok(width @ 1..=128) => width,
+        _ => Err(Error::InvalidBitWidthHint(width.to_string()))?,
+    };
+    let (signed, digits) = match digits.strip_prefix(['s', 'S']) {
+        Some(digits) => (true, digits),
+        None => (false, digits),
+    };
+    let radix = match digits.as_bytes()[0].to_ascii_lowercase() {
+        b'h' => 16,
+        b'd' => 10,
+        b'o' => 8,
+        _ => 2,
     };
-    Integer::from_str_radix(token, 10)
-        .map(|n| n * unit)
-        .map_err(Error::from)
+    let value = u128::from_str_radix(&digits[1..].replace('_', ""), radix)?;
+    let mask = u128::MAX >> (128 - width);
+    let mut literal = IntegerLiteral::new((value & mask) as Integer, radix);
+    if signed && width < 128 && literal.value >> (width - 1) != 0 {
+        literal.value -= 1 << width;
+    }
+    literal.width = Some(width);
+    literal.truncated = value & !mask != 0;
+    Ok(literal)
 }
 
-fn hex_number(lex: &mut Lexer<LexToken>) -> Result<Integer, Error> {
-    lex.slice()
-        .strip_prefix("0x")
-        .ok_or(Error::InvalidToken)
-        .and_then(|s| Integer::from_str_radix(s, 16).map_err(Error::from))
+/// Parse an integer literal matched by the regexes of [`LexToken::Integer`]
+fn parse_integer(slice: &str) -> Result<IntegerLiteral, Error> {
+    if let Some((radix, digits)) = slice.split_once('#') {
+        return parse_digits(digits, check_radix(radix.parse()?)?);
+    }
+    let lower = slice.to_ascii_lowercase();
+    let trimmed = lower.trim_end_matches(['u', 'l']);
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = trimmed.strip_prefix(prefix).filter(|d| !d.is_empty()) {
+            return parse_digits(digits, radix);
+        }
+    }
+    if let Some(digits) = trimmed.strip_suffix('b') {
+        return parse_digits(digits, 2);
+    }
+
+    let (number, shift) = match trimmed.strip_suffix(['k', 'm', 'g', 't', 'p']) {
+        Some(number) => {
+            let shift = match trimmed.as_bytes()[trimmed.len() - 1] {
+                b'k' => 10,
+                b'm' => 20,
+                b'g' => 30,
+                b't' => 40,
+                _ => 50,
+            };
+            (number, shift)
+        }
+        None => (trimmed, 0),
+    };
+    let number = number.replace(['_', '\''], "");
+    let value = if number.contains('.') {
+        let value = Float::from_str(&number)? * (1u64 << shift) as Float;
+        if value.fract() != 0.0 {
+            Err(Error::Custom(format!("{slice} is not an integer")))?;
+        }
+        value as Integer
+    } else {
+        Integer::from_str_radix(&number, 10)?
+            .checked_mul(1 << shift)
+            .ok_or(Error::Overflow)?
+    };
+    Ok(IntegerLiteral::new(value, 10))
 }
 
-fn oct_number(lex: &mut Lexer<LexToken>) -> Result<Integer, Error> {
-    lex.slice()
-        .strip_prefix("0o")
-        .ok_or(Error::InvalidToken)
-        .and_then(|s| Integer::from_str_radix(s, 8).map_err(Error::from))
+/// Parse digits in the radix, separated by `_` or `'`
+fn parse_digits(digits: &str, radix: u32) -> Result<IntegerLiteral, Error> {
+    let value = Integer::from_str_radix(&digits.replace(['_', '\''], ""), radix)?;
+    Ok(IntegerLiteral::new(value, radix))
 }
 
-fn bin_number(lex: &mut Lexer<LexToken>) -> Result<Integer, Error> {
-    lex.slice()
-        .strip_prefix("0b")
-        .ok_or(Error::InvalidToken)
-        .and_then(|s| Integer::from_str_radix(s, 2).map_err(Error::from))
+/// Check the radix is in the range of 2 to 36
+pub fn check_radix(radix: Integer) -> Result<u32, Error> {
+    match radix {
+        2..=36 => Ok(radix as u32),
+        _ => Err(Error::InvalidRadix(radix)),
+    }
 }
 
 fn store_identifier(lex: &mut Lexer<LexToken>) -> Option<String> {
     Some(lex.slice().to_string())
 }
 
-fn bit_width(lex: &mut Lexer<LexToken>) -> Result<usize, Error> {
-    lex.slice()
-        .strip_suffix(['^', '~', '!'])
-        .ok_or(Error::InvalidToken)
-        .and_then(|s| match s {
-            "b" => Ok(8),
-            "w" => Ok(16),
-            "dw" => Ok(32),
-            "l" => Ok(64),
-            "ll" => Ok(128),
-            "" => Ok(32),
-            _ => Err(Error::InvalidBitWidthHint(s.to_string())),
-        })
+fn bit_width(lex: &mut Lexer<LexToken>) -> Result<Option<usize>, Error> {
+    let hint = lex
+        .slice()
+        .trim_end_matches(['^', '~', '!', '<', '>', '-', '+', '*']);
+    let hint = hint
+        .strip_suffix("rol")
+        .or_else(|| hint.strip_suffix("ror"))
+        .unwrap_or(hint);
+    parse_width_hint(hint)
+}
+
+/// Parse a bit width hint: `b`, `w`, `dw`, `l`, `ll`, `uN` or `{N}` with N from 1 to 128,
+/// and `None` for an empty hint
+pub fn parse_width_hint(hint: &str) -> Result<Option<usize>, Error> {
+    let width = match hint {
+        "" => return Ok(None),
+        "b" => Some(8),
+        "w" => Some(16),
+        "dw" => Some(32),
+        "l" => Some(64),
+        "ll" => Some(128),
+        _ => hint
+            .strip_prefix('u')
+            .or_else(|| hint.strip_prefix('{')?.strip_suffix('}'))
+            .and_then(|width| width.parse().ok()),
+    };
+    match width {
+        Some(width @ 1..=128) => Ok(Some(width)),
+        _ => Err(Error::InvalidBitWidthHint(hint.to_string())),
+    }
+}
+
+fn c_only(lex: &mut Lexer<LexToken>) -> Result<(), Error> {
+    match lex.extras {
+        Dialect::C => Ok(()),
+        Dialect::Rcal => Err(Error::Custom(format!(
+            "{} is only supported in the C dialect",
+            lex.slice()
+        ))),
+    }
+}
+
+fn no_width(_lex: &mut Lexer<LexToken>) -> Result<Option<usize>, Error> {
+    Ok(None)
 }
 
 fn science_notation(lex: &mut Lexer<LexToken>) -> Result<Float, Error> {
@@ -167,3 +910,244 @@ fn store_string(lex: &mut Lexer<LexToken>) -> Result<String, Error> {
 fn bit_number(bit_number: &mut Lexer<LexToken>) -> Result<Integer, Error> {
     Ok(bit_number.slice()[3..].parse()?)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integer_literals() {
+        let cases: &[(&str, Integer, u32)] = &[
+            ("1234", 1234, 10),
+            ("1_000", 1000, 10),
+            ("1'000'000", 1_000_000, 10),
+            ("4k", 4096, 10),
+            ("2M", 2 << 20, 10),
+            ("1.5k", 1536, 10),
+            ("0.5m", 512 << 10, 10),
+            ("10u", 10, 10),
+            ("10UL", 10, 10),
+            ("10ull", 10, 10),
+            ("10LLU", 10, 10),
+            ("0xFFFF_0000", 0xFFFF_0000, 16),
+            ("0XFF", 0xFF, 16),
+            ("0x10UL", 0x10, 16),
+            ("0x1b", 0x1B, 16),
+            ("0FFh", 0xFF, 16),
+            ("1F_FFh", 0x1FFF, 16),
+            ("0o17", 0o17, 8),
+            ("0O17u", 0o17, 8),
+            ("0b1010", 0b1010, 2),
+            ("0B1010", 0b1010, 2),
+            ("0b1010_0101", 0b1010_0101, 2),
+            ("1010b", 0b1010, 2),
+            ("0b", 0, 2),
+            ("36#ZZ", 1295, 36),
+            ("16#dead_beef", 0xDEAD_BEEF, 16),
+        ];
+        for (input, value, radix) in cases {
+            match tokenize(input).as_deref() {
+                Ok([LexToken::Integer(literal)]) => {
+                    assert_eq!((literal.value, literal.radix), (*value, *radix), "{input}")
+                }
+                tokens => panic!("{input}: {tokens:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_c_literals() {
+        let options = LexOptions {
+            dialect: Dialect::C,
+            ..LexOptions::default()
+        };
+        let cases = [
+            ("10", 10, 10, false, false),
+            ("10u", 10, 10, true, false),
+            ("0x10UL", 0x10, 16, true, true),
+            ("10ll", 10, 10, false, true),
+            ("010", 8, 8, false, false),
+            ("017lu", 0o17, 8, true, true),
+            ("0", 0, 10, false, false),
+            ("'A'", 65, 10, false, false),
+        ];
+        for (input, value, radix, unsigned, long) in cases {
+            match tokenize_with(input, &options).as_deref() {
+                Ok([LexToken::Integer(literal)]) => assert_eq!(
+                    (literal.value, literal.radix, literal.unsigned, literal.long),
+                    (value, radix, unsigned, long),
+                    "{input}"
+                ),
+                tokens => panic!("{input}: {tokens:?}"),
+            }
+        }
+        assert_eq!(
+            tokenize_with("!1ul<<2", &options),
+            Ok(vec![
+                LexToken::LogicalNot,
+                LexToken::Integer(IntegerLiteral {
+                    unsigned: true,
+                    long: true,
+                    ..IntegerLiteral::new(1, 10)
+                }),
+                LexToken::LeftShift(None),
+                LexToken::Integer(IntegerLiteral::new(2, 10)),
+            ])
+        );
+        assert!(tokenize("1ul<<2").is_err());
+        assert!(tokenize_with("08", &options).is_err());
+    }
+
+    #[test]
+    fn test_sized_literals() {
+        let cases: &[(&str, Integer, u32, usize, bool)] = &[
+            ("32'hDEAD_BEEF", 0xDEAD_BEEF, 16, 32, false),
+            ("8'b1010_0101", 0b1010_0101, 2, 8, false),
+            ("12'd4095", 4095, 10, 12, false),
+            ("12'o7777", 0o7777, 8, 12, false),
+            ("8'H1ff", 0xFF, 16, 8, true),
+            ("8'sh80", -128, 16, 8, false),
+            ("8'sh7F", 127, 16, 8, false),
+            (
+                "128'hFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF",
+                -1,
+                16,
+                128,
+                false,
+            ),
+        ];
+        for (input, value, radix, width, truncated) in cases {
+            let tokens = tokenize(input);
+            let Ok([LexToken::Integer(literal)]) = tokens.as_deref() else {
+                panic!("{input}: {tokens:?}");
+            };
+            assert_eq!(
+                (
+                    literal.value,
+                    literal.radix,
+                    literal.width,
+                    literal.truncated
+                ),
+                (*value, *radix, Some(*width), *truncated),
+                "{input}"
+            );
+        }
+        assert_eq!(tokenize("'1"), Ok(vec![LexToken::Fill(true)]));
+        assert_eq!(
+            tokenize("'0 | 8'h1"),
+            Ok(vec![
+                LexToken::Fill(false),
+                LexToken::BitOr,
+                LexToken::Integer(IntegerLiteral {
+                    value: 1,
+                    radix: 16,
+                    width: Some(8),
+                    truncated: false,
+                    unsigned: false,
+                    long: false,
+                })
+            ])
+        );
+        assert_eq!(
+            tokenize("'10'"),
+            Ok(vec![LexToken::String("10".to_string())])
+        );
+        assert_eq!(tokenize("'1'"), Ok(vec![LexToken::String("1".to_string())]));
+        assert!(tokenize("0'h1").is_err());
+        assert!(tokenize("129'h1").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let c = LexOptions {
+            dialect: Dialect::C,
+            ..LexOptions::default()
+        };
+        for (input, options) in [
+            (
+                "8'hFF + 12'd5 u24^ 0x1F {12}rol bit3 - 'abc' * 1.5 w>>> 2 ^^ (x) [y], ~0b101 36#ZZ",
+                LexOptions::default(),
+            ),
+            ("!1ul << 2 <= 3u && x ? 010 : 1 != 2 || 'A' % 0x10L", c),
+        ] {
+            let tokens = tokenize_with(input, &options).unwrap();
+            let displayed: Vec<String> = tokens.iter().map(LexToken::to_string).collect();
+            assert_eq!(
+                tokenize_with(&displayed.join(" "), &options),
+                Ok(tokens),
+                "{input}"
+            );
+        }
+        let tokens = tokenize("8'hFF u24^ 0x1b").unwrap();
+        assert_eq!(
+            Error::ExpectToken(tokens[1].clone(), tokens[0].clone()).to_string(),
+            "Expect `{24}^`, got `8'hFF`"
+        );
+        assert_eq!(
+            Error::ExpectOperand(tokens[2].clone()).to_string(),
+            "Expect operand, got `0x1B`"
+        );
+    }
+
+    #[test]
+    fn test_literal_boundaries() {
+        let int = |value, radix| LexToken::Integer(IntegerLiteral::new(value, radix));
+        let cases: &[(&str, Vec<LexToken>)] = &[
+            (
+                "0x1b^2",
+                vec![int(0x1B, 16), LexToken::BitXor(None), int(2, 10)],
+            ),
+            (
+                "101 b^ 3",
+                vec![int(101, 10), LexToken::BitXor(Some(8)), int(3, 10)],
+            ),
+            (
+                "101b ^ 3",
+                vec![int(5, 2), LexToken::BitXor(None), int(3, 10)],
+            ),
+            ("bit3", vec![LexToken::Bit(3)]),
+            ("1.5", vec![LexToken::Float(1.5)]),
+            (
+                "ascii(1,'a')",
+                vec![
+                    LexToken::Custom("ascii".to_string()),
+                    LexToken::OpenParenthesis,
+                    int(1, 10),
+                    LexToken::Comma,
+                    LexToken::String("a".to_string()),
+                    LexToken::CloseParenthesis,
+                ],
+            ),
+            ("0FFh", vec![int(0xFF, 16)]),
+            (
+                "0Fh+1",
+                vec![int(0xF, 16), LexToken::Plus(None), int(1, 10)],
+            ),
+            (
+                "0F h",
+                vec![
+                    int(0, 10),
+                    LexToken::Custom("F".to_string()),
+                    LexToken::Custom("h".to_string()),
+                ],
+            ),
+        ];
+        for (input, tokens) in cases {
+            assert_eq!(tokenize(input).as_ref(), Ok(tokens), "{input}");
+        }
+
+        for input in [
+            "101b^3",
+            "10l~0",
+            "0x1ul^2",
+            "1.3k",
+            "170141183460469231731687303715884105727k",
+        ] {
+            assert!(tokenize(input).is_err(), "{input}");
+        }
+        assert_eq!(
+            tokenize("1 + 101b~0").unwrap_err().kind(),
+            "AmbiguousLiteral"
+        );
+    }
+}
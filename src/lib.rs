@@ -1,16 +1,33 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
     num::{ParseFloatError, ParseIntError},
+    ops::Range,
+    sync::Arc,
 };
 
+use builder::EvaluatorBuilder;
 use expr::{
+    c_dialect::{c_type_width, parse_c_expr_spanned},
+    context::{EvalContext, Settings},
+    function::{Function, HandlerFunction, NativeFn, NativeFunction},
     operand::{Operand, OperandType},
-    operator::{default_handlers, Operator, OperatorAction},
-    parse_expr, to_suffix, ExprToken,
+    operator::{
+        stack_handler, Associativity, Handler, InfixOperator, Operator, OperatorAction,
+        RegisteredHandler, SharedHandler, Signature, SizedHandler,
+    },
+    Expr, Parser, Visitor,
+};
+use format::{NumberFormat, Sign};
+use lex::{
+    dominant_radix, tokenize, tokenize_spanned, Dialect, IntegerLiteral, LexOptions, LexToken,
 };
-use lex::{tokenize, LexToken};
+use serde::{ser::SerializeStruct as _, Serialize, Serializer};
 
+pub mod builder;
+pub mod config;
 pub mod expr;
+pub mod format;
 pub mod lex;
 
 pub type Integer = i128;
@@ -40,67 +57,808 @@ pub enum Error {
     },
     #[error("{0}")]
     Custom(String),
-    #[error("Expect operator, got {0:?}")]
+    #[error("Expect operator, got `{0}`")]
     ExpectOperator(LexToken),
-    #[error("Expect operand, got {0:?}")]
+    #[error("Expect operand, got `{0}`")]
     ExpectOperand(LexToken),
-    #[error("Expect {0:?}, got {1:?}")]
+    #[error("Expect `{0}`, got `{1}`")]
     ExpectToken(LexToken, LexToken),
     #[error("Expression ends unexpectedly")]
     UnexpectedEnd,
+    #[error("Unbound variable: {0}")]
+    UnboundVariable(String),
+    #[error("{name} expects {expected}, got {got}")]
+    ArgumentCount {
+        name: String,
+        expected: Signature,
+        got: usize,
+    },
+    #[error("Integer overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Ambiguous literal {0}, separate the bit width hint from it with a space")]
+    AmbiguousLiteral(String),
+    #[error("Ambiguous implicit multiplication {0}, separate the operands with `*`")]
+    AmbiguousJuxtaposition(String),
+    #[error("Invalid radix {0}, expected 2 to 36")]
+    InvalidRadix(Integer),
+    #[error("Invalid operator {symbol}: {reason}")]
+    InvalidOperator { symbol: String, reason: String },
+    #[error("Line {line}: {error}")]
+    Config { line: usize, error: Box<Error> },
+    /// Error of the lexer or the parser with the byte range of the formula it is about.
+    /// Evaluation errors, like a division by zero, have no span
+    #[error("{error}")]
+    Spanned {
+        error: Box<Error>,
+        span: Range<usize>,
+    },
+}
+
+impl Error {
+    /// Name of the error variant, used as a stable identifier in machine readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidToken => "InvalidToken",
+            Error::ParseIntError(_) => "ParseIntError",
+            Error::ParseFloatError(_) => "ParseFloatError",
+            Error::InvalidBitWidthHint(_) => "InvalidBitWidthHint",
+            Error::NotEnoughOperand => "NotEnoughOperand",
+            Error::NoMatchingHandler { .. } => "NoMatchingHandler",
+            Error::RedundantOperand(_) => "RedundantOperand",
+            Error::InvalidDataType { .. } => "InvalidDataType",
+            Error::Custom(_) => "Custom",
+            Error::ExpectOperator(_) => "ExpectOperator",
+            Error::ExpectOperand(_) => "ExpectOperand",
+            Error::ExpectToken(_, _) => "ExpectToken",
+            Error::UnexpectedEnd => "UnexpectedEnd",
+            Error::UnboundVariable(_) => "UnboundVariable",
+            Error::ArgumentCount { .. } => "ArgumentCount",
+            Error::Overflow => "Overflow",
+            Error::DivisionByZero => "DivisionByZero",
+            Error::InvalidRadix(_) => "InvalidRadix",
+            Error::AmbiguousLiteral(_) => "AmbiguousLiteral",
+            Error::AmbiguousJuxtaposition(_) => "AmbiguousJuxtaposition",
+            Error::InvalidOperator { .. } => "InvalidOperator",
+            Error::Config { .. } => "Config",
+            Error::Spanned { error, .. } => error.kind(),
+        }
+    }
+
+    /// Byte range of the formula where the error occurred, if known
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::Spanned { span, .. } => Some(span.clone()),
+            _ => None,
+        }
+    }
+
+    /// The error without the span of [`Error::Spanned`]
+    ///
+    /// ```
+    /// use rcal::{lex::LexToken, Error, Evaluator};
+    ///
+    /// let error = Evaluator::default().eval("1 + )").unwrap_err();
+    /// assert_eq!(error.span(), Some(4..5));
+    /// assert_eq!(
+    ///     error.without_span(),
+    ///     &Error::ExpectOperand(LexToken::CloseParenthesis)
+    /// );
+    /// ```
+    pub fn without_span(&self) -> &Error {
+        match self {
+            Error::Spanned { error, .. } => error.without_span(),
+            error => error,
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("span", &self.span())?;
+        state.end()
+    }
 }
 
+/// Evaluator of expressions, which can be shared between threads.
+///
+/// Cloning is cheap: the clone shares the registered handlers, including their state,
+/// while its settings and variables are independent of the original
+#[derive(Clone)]
 pub struct Evaluator {
-    operators: HashMap<Operator, OperatorAction>,
+    settings: Settings,
+    variables: HashMap<String, Operand>,
+    operators: Arc<HashMap<Operator, Arc<RegisteredHandler>>>,
+    /// Built-in handlers of the operators with a bit width hint, by the operator without the hint
+    sized: Arc<HashMap<Operator, SizedHandler>>,
+    /// Functions by their names and aliases
+    functions: Arc<BTreeMap<String, Arc<dyn Function>>>,
+    /// Infix operators by their symbols
+    infix: Arc<BTreeMap<String, InfixOperator>>,
 }
 
+/// Evaluator with every built-in set enabled by the cargo features
 impl Default for Evaluator {
     fn default() -> Self {
-        let operators = HashMap::from_iter(default_handlers());
-        Self { operators }
+        Self::builder()
+            .build()
+            .expect("the default settings are valid")
     }
 }
 
 impl Evaluator {
+    /// Builder starting with the standard built-in sets
+    ///
+    /// ```
+    /// use rcal::{
+    ///     builder::EvaluatorBuilder,
+    ///     expr::{builtin::BuiltinSet, context::DivisionMode, operand::Operand},
+    ///     Evaluator,
+    /// };
+    ///
+    /// # #[cfg(feature = "arithmetic")] {
+    /// let evaluator = Evaluator::builder().division(DivisionMode::Float).build().unwrap();
+    /// assert_eq!(evaluator.eval("7 / 2").unwrap(), Operand::Float(3.5));
+    /// # }
+    ///
+    /// let evaluator = EvaluatorBuilder::empty().with_set(BuiltinSet::Bytes).build().unwrap();
+    /// assert_eq!(evaluator.eval("rev(0x1234)").unwrap(), Operand::Integer(0x3412));
+    /// assert!(evaluator.eval("1 + 1").is_err());
+    /// ```
+    pub fn builder() -> EvaluatorBuilder {
+        EvaluatorBuilder::standard()
+    }
+
+    /// Evaluator without any operator, function or variable
+    fn empty(settings: Settings) -> Self {
+        Self {
+            settings,
+            variables: HashMap::new(),
+            operators: Arc::default(),
+            sized: Arc::default(),
+            functions: Arc::default(),
+            infix: Arc::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Set a variable available to every evaluation of this evaluator
+    pub fn set_variable(&mut self, name: &str, value: Operand) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn variables(&self) -> &HashMap<String, Operand> {
+        &self.variables
+    }
+
+    /// Register the stack-style handler of an operator.
+    /// Handlers of [`Operator::Custom`] are registered as functions accepting any number of arguments
     pub fn insert_op_handler(&mut self, op: Operator, handler: OperatorAction) {
-        self.operators.insert(op, handler);
+        self.insert_handler_impl(op, RegisteredHandler::Pure(stack_handler(handler)));
+    }
+
+    /// Register the handler of an operator.
+    /// Handlers of [`Operator::Custom`] are registered as functions accepting any number of arguments.
+    /// The handler may keep state, so it is never evaluated at compile time,
+    /// and calls from multiple threads are serialized
+    pub fn insert_handler(&mut self, op: Operator, handler: Handler) {
+        self.insert_handler_impl(op, RegisteredHandler::Stateful(SharedHandler::new(handler)));
+    }
+
+    /// Register the handler of a built-in operator, which is pure
+    #[cfg(any(feature = "arithmetic", feature = "bitwise"))]
+    pub(crate) fn insert_builtin(&mut self, op: Operator, handler: expr::operator::PureHandler) {
+        self.insert_handler_impl(op, RegisteredHandler::Pure(handler));
+    }
+
+    /// Register the handler of a built-in operator for every bit width hint
+    #[cfg(any(feature = "arithmetic", feature = "bitwise"))]
+    pub(crate) fn insert_sized_builtin(&mut self, op: Operator, handler: SizedHandler) {
+        Arc::make_mut(&mut self.sized).insert(op.with_width(None), handler);
+    }
+
+    fn insert_handler_impl(&mut self, op: Operator, handler: RegisteredHandler) {
+        match op {
+            Operator::Custom(name) => self.register_function(Box::new(HandlerFunction {
+                name,
+                signature: Signature::Variadic { min: 0 },
+                handler,
+            })),
+            op => {
+                Arc::make_mut(&mut self.operators).insert(op, Arc::new(handler));
+            }
+        }
+    }
+
+    /// Register a stack-style function handler,
+    /// whose argument count is checked against the signature before calling it
+    pub fn insert_function(&mut self, name: &str, signature: Signature, handler: OperatorAction) {
+        self.register_function(Box::new(HandlerFunction {
+            name: name.to_string(),
+            signature,
+            handler: RegisteredHandler::Pure(stack_handler(handler)),
+        }));
+    }
+
+    /// Register a function handler,
+    /// whose argument count is checked against the signature before calling it.
+    /// The handler may keep state, so it is never evaluated at compile time
+    ///
+    /// ```
+    /// use rcal::{expr::{operand::Operand, operator::Signature}, Evaluator};
+    ///
+    /// # #[cfg(feature = "arithmetic")] {
+    /// let mut evaluator = Evaluator::default();
+    /// let mut count = 0;
+    /// evaluator.insert_function_handler(
+    ///     "next",
+    ///     Signature::Fixed(0),
+    ///     Box::new(move |_, _| {
+    ///         count += 1;
+    ///         Ok(Operand::Integer(count))
+    ///     }),
+    /// );
+    /// assert_eq!(evaluator.eval("next() * 10 + next()").unwrap(), Operand::Integer(12));
+    /// # }
+    /// ```
+    pub fn insert_function_handler(&mut self, name: &str, signature: Signature, handler: Handler) {
+        self.register_function(Box::new(HandlerFunction {
+            name: name.to_string(),
+            signature,
+            handler: RegisteredHandler::Stateful(SharedHandler::new(handler)),
+        }));
+    }
+
+    /// Register a closure with typed parameters as a function.
+    /// Arguments are converted to the parameter types, and the argument count is checked against the closure.
+    /// The closure may have state, like a counter, so its calls are never folded by [`Evaluator::compile`],
+    /// see [`Evaluator::register_pure_fn`]
+    ///
+    /// ```
+    /// use rcal::{expr::operand::Operand, Evaluator, Integer};
+    ///
+    /// let mut evaluator = Evaluator::default();
+    /// evaluator.register_fn("mac", |a: Integer, b: Integer, c: Integer| a * b + c);
+    /// assert_eq!(evaluator.eval("mac(2, 3, 4)").unwrap(), Operand::Integer(10));
+    /// ```
+    pub fn register_fn<Args: 'static, F: NativeFn<Args> + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        function: F,
+    ) {
+        self.register_function(Box::new(NativeFunction {
+            name: name.to_string(),
+            function,
+            args: PhantomData,
+            pure: false,
+        }));
+    }
+
+    /// Register a closure whose result only depends on its arguments, like [`Evaluator::register_fn`].
+    /// Its calls with constant arguments are folded by [`Evaluator::compile`]
+    ///
+    /// ```
+    /// use rcal::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::default();
+    /// evaluator.register_pure_fn("square", |a: f64| a * a);
+    /// let compiled = evaluator.compile("square(3)").unwrap();
+    /// assert_eq!(compiled.expr().to_string(), "9.0");
+    /// ```
+    pub fn register_pure_fn<Args: 'static, F: NativeFn<Args> + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        function: F,
+    ) {
+        self.register_function(Box::new(NativeFunction {
+            name: name.to_string(),
+            function,
+            args: PhantomData,
+            pure: true,
+        }));
+    }
+
+    /// Register a function with its name and aliases
+    pub fn register_function(&mut self, function: Box<dyn Function>) {
+        let function: Arc<dyn Function> = Arc::from(function);
+        let functions = Arc::make_mut(&mut self.functions);
+        for name in std::iter::once(function.name()).chain(function.aliases().iter().copied()) {
+            functions.insert(name.to_string(), function.clone());
+        }
+    }
+
+    /// Register an infix operator calling `function` with its two operands.
+    /// The symbol is either a word, like `rotl`, or made of punctuation characters, like `<=>`.
+    /// The precedence follows [`Operator::precedence`], so 1 binds tighter than any built-in binary operator
+    ///
+    /// ```
+    /// use rcal::{expr::{operand::Operand, operator::Associativity}, Evaluator, Integer};
+    ///
+    /// # #[cfg(feature = "arithmetic")] {
+    /// let mut evaluator = Evaluator::default();
+    /// evaluator.register_fn("cmp", |a: Integer, b: Integer| a.cmp(&b) as Integer);
+    /// evaluator.register_infix("<=>", "cmp", 9, Associativity::Left).unwrap();
+    /// assert_eq!(evaluator.eval("1 + 1 <=> 3").unwrap(), Operand::Integer(-1));
+    /// assert!(evaluator.register_infix("<<", "cmp", 9, Associativity::Left).is_err());
+    /// # }
+    /// ```
+    pub fn register_infix(
+        &mut self,
+        symbol: &str,
+        function: &str,
+        precedence: usize,
+        associativity: Associativity,
+    ) -> Result<(), Error> {
+        let invalid = |reason: &str| Error::InvalidOperator {
+            symbol: symbol.to_string(),
+            reason: reason.to_string(),
+        };
+        if precedence == 0 || precedence == usize::MAX {
+            Err(invalid("precedence must be at least 1"))?;
+        }
+        let is_word = symbol.starts_with(|c: char| c.is_ascii_alphabetic())
+            && symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_word {
+            if symbol == "x" || tokenize(symbol) != Ok(vec![LexToken::Custom(symbol.to_string())]) {
+                Err(invalid("conflicts with a built-in token"))?;
+            }
+        } else {
+            if symbol.is_empty()
+                || !symbol
+                    .chars()
+                    .all(|c| c.is_ascii_punctuation() && !"()[],.'\"_".contains(c))
+            {
+                Err(invalid(
+                    "must be a word or made of punctuation characters other than ()[],.'\"_",
+                ))?;
+            }
+            if let Ok([token]) = tokenize(symbol).as_deref() {
+                Err(invalid(&format!(
+                    "conflicts with the built-in token `{token}`"
+                )))?;
+            }
+        }
+        Arc::make_mut(&mut self.infix).insert(
+            symbol.to_string(),
+            InfixOperator {
+                symbol: symbol.to_string(),
+                function: function.to_string(),
+                precedence,
+                associativity,
+            },
+        );
+        Ok(())
+    }
+
+    /// Look up a function by its name or one of its aliases
+    pub fn function(&self, name: &str) -> Option<&dyn Function> {
+        self.functions.get(name).map(|function| function.as_ref())
+    }
+
+    /// Registered functions, sorted by name
+    pub fn functions(&self) -> impl Iterator<Item = &dyn Function> {
+        self.functions
+            .iter()
+            .filter(|(name, function)| function.name() == name.as_str())
+            .map(|(_, function)| function.as_ref())
     }
 
     pub fn eval(&self, formula: &str) -> Result<Operand, Error> {
-        let lex = tokenize(formula)?;
-        let expr_tokens = parse_expr(&mut lex.as_slice().into())?;
-        let suffix = to_suffix(&expr_tokens);
-        self.consume_operators(&suffix)
-    }
-
-    pub fn consume_operators(&self, expr: &[ExprToken]) -> Result<Operand, Error> {
-        let mut operands = vec![];
-        for token in expr {
-            match token {
-                ExprToken::Operand(op) => operands.push(op.clone()),
-                ExprToken::Operator(op) => self
-                    .operators
-                    .get(op)
-                    .ok_or(Error::NoMatchingHandler { op: op.clone() })?(
-                    &mut operands
-                )?,
+        let expr = self.parse(formula)?;
+        self.evaluate(&expr)
+    }
+
+    /// Lex and parse the formula with the registered infix operators.
+    /// Errors are wrapped in [`Error::Spanned`] with the span of the token they are about
+    pub fn parse(&self, formula: &str) -> Result<Expr, Error> {
+        let (lex, spans): (Vec<LexToken>, Vec<Range<usize>>) =
+            self.tokenize_spanned(formula)?.into_iter().unzip();
+        if self.settings.dialect == Dialect::C {
+            return parse_c_expr_spanned(&lex, &spans);
+        }
+        Parser::new(&lex)
+            .with_spans(&spans)
+            .with_infix(&self.infix)
+            .with_default_width(self.settings.default_width)
+            .with_implicit_mul(self.settings.implicit_mul)
+            .parse()
+    }
+
+    /// Radix most integer literals of the formula are written in, see [`dominant_radix`]
+    ///
+    /// ```
+    /// use rcal::Evaluator;
+    ///
+    /// let evaluator = Evaluator::default();
+    /// assert_eq!(evaluator.input_radix("0x1000 + 0x200 << 4").unwrap(), Some(16));
+    /// assert_eq!(evaluator.input_radix("0b11 | 0o7 | 0x1 | 0o3").unwrap(), Some(8));
+    /// assert_eq!(evaluator.input_radix("1 + 2").unwrap(), Some(10));
+    /// ```
+    pub fn input_radix(&self, formula: &str) -> Result<Option<u32>, Error> {
+        Ok(dominant_radix(&self.tokenize(formula)?))
+    }
+
+    /// Widest bit width of the sized literals of the formula, like `8'hFF`,
+    /// and of the hexadecimal and binary literals if [`Settings::infer_width`] is set
+    ///
+    /// ```
+    /// use rcal::Evaluator;
+    ///
+    /// let evaluator = Evaluator::default();
+    /// assert_eq!(evaluator.input_width("12'hFFF & 8'h0F").unwrap(), Some(12));
+    /// assert_eq!(evaluator.input_width("0xFF").unwrap(), None);
+    /// ```
+    pub fn input_width(&self, formula: &str) -> Result<Option<usize>, Error> {
+        Ok(self
+            .tokenize(formula)?
+            .iter()
+            .filter_map(|token| match token {
+                LexToken::Integer(literal) => literal.width,
+                _ => None,
+            })
+            .max())
+    }
+
+    /// Bit width of the integer type C gives the formula in the C dialect,
+    /// `None` in the rcal dialect and for floating point expressions or expressions with variables
+    ///
+    /// ```
+    /// use rcal::{lex::Dialect, Evaluator};
+    ///
+    /// let evaluator = Evaluator::builder().dialect(Dialect::C).build().unwrap();
+    /// assert_eq!(evaluator.type_width("~0").unwrap(), Some(32));
+    /// assert_eq!(evaluator.type_width("(char)1 << 40ul").unwrap(), Some(32));
+    /// assert_eq!(evaluator.type_width("1ul << 40").unwrap(), Some(64));
+    /// assert_eq!(evaluator.type_width("1.5 * 2").unwrap(), None);
+    /// ```
+    pub fn type_width(&self, formula: &str) -> Result<Option<usize>, Error> {
+        match self.settings.dialect {
+            Dialect::C => c_type_width(&self.tokenize(formula)?),
+            Dialect::Rcal => Ok(None),
+        }
+    }
+
+    /// Warnings about the literals of the formula, like sized literals truncated to their width
+    pub fn warnings(&self, formula: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .tokenize(formula)?
+            .iter()
+            .filter_map(|token| match token {
+                LexToken::Integer(IntegerLiteral {
+                    value,
+                    width: Some(width),
+                    truncated: true,
+                    ..
+                }) => Some(format!(
+                    "sized literal does not fit into {width} bits, truncated to {}",
+                    NumberFormat::new(16)
+                        .prefix(true)
+                        .uppercase(true)
+                        .sign(Sign::TwosComplement(*width as u32))
+                        .format(*value)
+                )),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn tokenize(&self, formula: &str) -> Result<Vec<LexToken>, Error> {
+        Ok(self
+            .tokenize_spanned(formula)?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect())
+    }
+
+    fn tokenize_spanned(&self, formula: &str) -> Result<Vec<(LexToken, Range<usize>)>, Error> {
+        let symbols: Vec<&str> = self
+            .infix
+            .keys()
+            .filter(|symbol| !symbol.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .map(String::as_str)
+            .collect();
+        let names: Vec<&str> = match self.settings.implicit_mul {
+            true => self
+                .variables
+                .keys()
+                .chain(self.functions.keys())
+                .map(String::as_str)
+                .collect(),
+            false => vec![],
+        };
+        tokenize_spanned(
+            formula,
+            &LexOptions {
+                symbols: &symbols,
+                ibase: self.settings.ibase,
+                infer_width: self.settings.infer_width,
+                dialect: self.settings.dialect,
+                implicit_mul: self.settings.implicit_mul && self.settings.dialect == Dialect::Rcal,
+                names: &names,
+            },
+        )
+    }
+
+    pub fn evaluate(&self, expr: &Expr) -> Result<Operand, Error> {
+        expr.accept(&mut Evaluation {
+            ctx: EvalContext {
+                evaluator: self,
+                bindings: &HashMap::new(),
+                depth: 0,
+            },
+        })
+    }
+
+    /// Lex and parse the formula once, folding its constant sub-expressions,
+    /// so it can be evaluated repeatedly with different variable bindings
+    pub fn compile(&self, formula: &str) -> Result<CompiledExpr, Error> {
+        let expr = self.parse(formula)?;
+        let mut folder = ConstantFolder {
+            evaluator: self,
+            variables: vec![],
+        };
+        let expr = expr.accept(&mut folder);
+        let mut variables = folder.variables;
+        variables.sort();
+        variables.dedup();
+        Ok(CompiledExpr {
+            evaluator: self.clone(),
+            expr,
+            variables,
+        })
+    }
+
+    fn apply(
+        &self,
+        ctx: &EvalContext,
+        op: &Operator,
+        operands: Vec<Operand>,
+    ) -> Result<Operand, Error> {
+        match op {
+            Operator::Custom(name) => return self.call(ctx, name, operands),
+            Operator::Infix(infix) => return self.call(ctx, &infix.function, operands),
+            Operator::Cast(ty, rounding) => {
+                let [operand] = expr::builtin::take(operands)?;
+                return operand.cast(*ty, *rounding);
+            }
+            _ => {}
+        }
+        let op = op.with_default_width(self.settings.default_width);
+        if let Some(handler) = self.operators.get(&op) {
+            return handler.call(&op, ctx, operands);
+        }
+        match (op.width(), self.sized.get(&op.with_width(None))) {
+            (Some(width), Some(handler)) => handler(width, operands),
+            _ => Err(Error::NoMatchingHandler { op }),
+        }
+    }
+
+    fn call(&self, ctx: &EvalContext, name: &str, args: Vec<Operand>) -> Result<Operand, Error> {
+        let function = self.function(name).ok_or(Error::NoMatchingHandler {
+            op: Operator::Custom(name.to_string()),
+        })?;
+        if !function.signature().accepts(args.len()) {
+            Err(Error::ArgumentCount {
+                name: name.to_string(),
+                expected: function.signature(),
+                got: args.len(),
+            })?;
+        }
+        function.call(ctx, args)
+    }
+
+    /// Whether the operation only depends on its operands
+    fn is_pure(&self, op: &Operator) -> bool {
+        match op {
+            Operator::Custom(name) => self.function(name).is_some_and(|f| f.is_pure()),
+            Operator::Infix(infix) => self.function(&infix.function).is_some_and(|f| f.is_pure()),
+            Operator::Cast(..) => true,
+            op => {
+                let op = op.with_default_width(self.settings.default_width);
+                match self.operators.get(&op) {
+                    Some(handler) => handler.is_pure(),
+                    None => op.width().is_some() && self.sized.contains_key(&op.with_width(None)),
+                }
             }
         }
+    }
+}
+
+/// Expression compiled by [`Evaluator::compile`].
+/// It keeps a clone of the evaluator, so registrations after compiling are not visible to it
+#[derive(Clone)]
+pub struct CompiledExpr {
+    evaluator: Evaluator,
+    expr: Expr,
+    variables: Vec<String>,
+}
+
+impl CompiledExpr {
+    /// The expression after constant folding
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Names of the variables in the expression, sorted
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluate with the bindings, which take precedence over the variables of the evaluator
+    pub fn eval_with(&self, bindings: &HashMap<String, Operand>) -> Result<Operand, Error> {
+        self.expr.accept(&mut Evaluation {
+            ctx: EvalContext {
+                evaluator: &self.evaluator,
+                bindings,
+                depth: 0,
+            },
+        })
+    }
+}
+
+/// Evaluates an [`Expr`] bottom-up with the handlers of an [`Evaluator`]
+struct Evaluation<'a> {
+    ctx: EvalContext<'a>,
+}
+
+impl Visitor for Evaluation<'_> {
+    type Output = Result<Operand, Error>;
+
+    fn visit_literal(&mut self, operand: &Operand) -> Self::Output {
+        Ok(operand.clone())
+    }
+
+    fn visit_variable(&mut self, name: &str) -> Self::Output {
+        self.ctx
+            .variable(name)
+            .cloned()
+            .ok_or_else(|| Error::UnboundVariable(name.to_string()))
+    }
+
+    fn visit_unary(&mut self, op: &Operator, operand: &Expr) -> Self::Output {
+        let operand = operand.accept(self)?;
+        self.ctx.evaluator.apply(&self.ctx, op, vec![operand])
+    }
+
+    fn visit_binary(&mut self, op: &Operator, lhs: &Expr, rhs: &Expr) -> Self::Output {
+        let lhs = lhs.accept(self)?;
+        let rhs = rhs.accept(self)?;
+        self.ctx.evaluator.apply(&self.ctx, op, vec![lhs, rhs])
+    }
+
+    fn visit_call(&mut self, name: &str, args: &[Expr]) -> Self::Output {
+        let args = args
+            .iter()
+            .map(|arg| arg.accept(self))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.ctx.call(name, args)
+    }
 
-        if operands.len() != 1 {
-            Err(Error::RedundantOperand(operands.len()))?;
+    fn visit_conditional(
+        &mut self,
+        condition: &Expr,
+        then: &Expr,
+        otherwise: &Expr,
+    ) -> Self::Output {
+        if condition.accept(self)?.is_true()? {
+            then.accept(self)
+        } else {
+            otherwise.accept(self)
         }
+    }
+}
 
-        operands.into_iter().last().ok_or(Error::NotEnoughOperand)
+/// Replaces the operations whose operands are all literals with their results.
+/// Operations that fail are kept, so the error is reported at evaluation
+struct ConstantFolder<'a> {
+    evaluator: &'a Evaluator,
+    variables: Vec<String>,
+}
+
+impl ConstantFolder<'_> {
+    fn fold(&self, expr: Expr) -> Expr {
+        let is_literal = |expr: &Expr| matches!(expr, Expr::Literal(_));
+        let constant = match &expr {
+            Expr::Unary { op, operand } => self.evaluator.is_pure(op) && is_literal(operand),
+            Expr::Binary { op, lhs, rhs } => {
+                self.evaluator.is_pure(op) && is_literal(lhs) && is_literal(rhs)
+            }
+            Expr::Call { name, args } => {
+                self.evaluator.is_pure(&Operator::Custom(name.clone()))
+                    && args.iter().all(is_literal)
+            }
+            Expr::Literal(_) | Expr::Variable(_) | Expr::Conditional { .. } => false,
+        };
+        if constant {
+            if let Ok(result) = self.evaluator.evaluate(&expr) {
+                return Expr::Literal(result);
+            }
+        }
+        expr
+    }
+}
+
+impl Visitor for ConstantFolder<'_> {
+    type Output = Expr;
+
+    fn visit_literal(&mut self, operand: &Operand) -> Self::Output {
+        Expr::Literal(operand.clone())
+    }
+
+    fn visit_variable(&mut self, name: &str) -> Self::Output {
+        self.variables.push(name.to_string());
+        Expr::Variable(name.to_string())
+    }
+
+    fn visit_unary(&mut self, op: &Operator, operand: &Expr) -> Self::Output {
+        let operand = operand.accept(self);
+        self.fold(Expr::Unary {
+            op: op.clone(),
+            operand: Box::new(operand),
+        })
+    }
+
+    fn visit_binary(&mut self, op: &Operator, lhs: &Expr, rhs: &Expr) -> Self::Output {
+        let lhs = lhs.accept(self);
+        let rhs = rhs.accept(self);
+        self.fold(Expr::Binary {
+            op: op.clone(),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+    }
+
+    fn visit_call(&mut self, name: &str, args: &[Expr]) -> Self::Output {
+        let args = args.iter().map(|arg| arg.accept(self)).collect();
+        self.fold(Expr::Call {
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    /// A literal condition is replaced with the branch it chooses
+    fn visit_conditional(
+        &mut self,
+        condition: &Expr,
+        then: &Expr,
+        otherwise: &Expr,
+    ) -> Self::Output {
+        let condition = condition.accept(self);
+        if let Expr::Literal(operand) = &condition {
+            if let Ok(truth) = operand.is_true() {
+                return if truth {
+                    then.accept(self)
+                } else {
+                    otherwise.accept(self)
+                };
+            }
+        }
+        Expr::Conditional {
+            condition: Box::new(condition),
+            then: Box::new(then.accept(self)),
+            otherwise: Box::new(otherwise.accept(self)),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use expr::operator::Operator;
-
     use super::*;
 
+    #[cfg(feature = "arithmetic")]
     fn test_func(operands: &mut Vec<Operand>) -> Result<(), Error> {
         let c = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
         let b = operands.pop().ok_or(Error::NotEnoughOperand)?.as_int()?;
@@ -112,6 +870,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
     fn test_basic() {
         let mut evaluator = Evaluator::default();
         evaluator.insert_op_handler(Operator::Custom("func".to_string()), Box::new(test_func));
@@ -153,4 +912,1030 @@ mod test {
             Operand::Integer(0x48564662)
         )
     }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_associativity() {
+        let evaluator = Evaluator::default();
+        assert_eq!(
+            evaluator.eval("2 ^^ 3 ^^ 2").unwrap(),
+            Operand::Integer(512)
+        );
+        assert_eq!(evaluator.eval("10 - 4 - 3").unwrap(), Operand::Integer(3));
+        assert_eq!(evaluator.eval("64 / 4 / 2").unwrap(), Operand::Integer(8));
+        assert_eq!(evaluator.eval("-2 ^^ 2").unwrap(), Operand::Integer(-4));
+        assert_eq!(evaluator.eval("2 ^^ -1.0").unwrap(), Operand::Float(0.5));
+        assert_eq!(evaluator.eval("- -~~3").unwrap(), Operand::Integer(3));
+        assert_eq!(
+            evaluator.eval("rev(rev(0x1234)) | 1 << 4 * 2").unwrap(),
+            Operand::Integer(0x1334)
+        );
+        assert_eq!(
+            evaluator.eval("1 + 2 3").unwrap_err().without_span(),
+            &Error::ExpectOperator(LexToken::Integer(lex::IntegerLiteral::new(3, 10)))
+        );
+        assert_eq!(
+            evaluator.eval("(1 + 2").unwrap_err().without_span(),
+            &Error::UnexpectedEnd
+        );
+        for formula in ["1 + 'a'", "'a' * 2", "'a' - 1"] {
+            assert_eq!(
+                evaluator.eval(formula),
+                Err(Error::InvalidDataType {
+                    expected: vec![OperandType::Integer, OperandType::Float],
+                    got: OperandType::String
+                }),
+                "{formula}"
+            );
+        }
+
+        let expr = expr::parse_expr(&tokenize("-2 ^^ 3 ^^ 2 * ~ascii('a', 1.5)").unwrap()).unwrap();
+        assert_eq!(expr.to_string(), "(-(2 ^^ (3 ^^ 2)) * ~ascii(\"a\", 1.5))");
+        assert_eq!(
+            expr::parse_expr(&tokenize(&expr.to_string()).unwrap()).unwrap(),
+            expr
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_compile() {
+        let evaluator = Evaluator::default();
+        let compiled = evaluator
+            .compile("(x & mask) >> 4 | (1 << 4) * rev(0x0102)")
+            .unwrap();
+        assert_eq!(compiled.variables(), ["mask", "x"]);
+        assert_eq!(compiled.expr().to_string(), "(((x & mask) >> 4) | 8208)");
+        for x in [0x00, 0x35, 0xFF] {
+            let bindings = HashMap::from([
+                ("x".to_string(), Operand::Integer(x)),
+                ("mask".to_string(), Operand::Integer(0xF0)),
+            ]);
+            assert_eq!(
+                compiled.eval_with(&bindings).unwrap(),
+                Operand::Integer((x & 0xF0) >> 4 | 0x2010)
+            );
+        }
+        assert_eq!(
+            compiled.eval_with(&HashMap::new()),
+            Err(Error::UnboundVariable("x".to_string()))
+        );
+
+        // folded negative literals keep their value when printed and parsed back
+        let bindings = HashMap::from([("x".to_string(), Operand::Integer(2))]);
+        for (formula, printed) in [
+            ("(0 - 2) ^^ x", "((-2) ^^ x)"),
+            ("x - (0 - 3)", "(x - (-3))"),
+            ("(0 - 1.5) * x", "((-1.5) * x)"),
+            (
+                "(1 << 127) + x",
+                "((-170141183460469231731687303715884105727 - 1) + x)",
+            ),
+        ] {
+            let compiled = evaluator.compile(formula).unwrap();
+            assert_eq!(compiled.expr().to_string(), printed);
+            let reparsed = evaluator.compile(printed).unwrap();
+            assert_eq!(
+                reparsed.eval_with(&bindings),
+                compiled.eval_with(&bindings),
+                "{formula}"
+            );
+        }
+        assert_eq!(
+            evaluator.parse("(-2)").unwrap(),
+            Expr::Unary {
+                op: Operator::Negate(None),
+                operand: Box::new(Expr::Literal(Operand::Integer(2)))
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "arithmetic")]
+    fn test_arity() {
+        let mut evaluator = Evaluator::default();
+        evaluator.insert_function("func", Signature::Fixed(3), Box::new(test_func));
+        evaluator.insert_function(
+            "sum",
+            Signature::Variadic { min: 1 },
+            Box::new(|operands| {
+                let sum = operands
+                    .drain(..)
+                    .try_fold(0, |sum, operand| Ok::<_, Error>(sum + operand.as_int()?))?;
+                operands.push(Operand::Integer(sum));
+                Ok(())
+            }),
+        );
+        assert_eq!(
+            evaluator.eval("rev(1, 2)").unwrap_err().to_string(),
+            "rev expects 1 argument, got 2"
+        );
+        assert_eq!(
+            evaluator.eval("1 + func(1)").unwrap_err().to_string(),
+            "func expects 3 arguments, got 1"
+        );
+        assert_eq!(
+            evaluator.eval("sum()").unwrap_err().to_string(),
+            "sum expects at least 1 argument, got 0"
+        );
+        assert_eq!(
+            evaluator.eval("1 + sum(2, 3, sum(4), 5)").unwrap(),
+            Operand::Integer(15)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "arithmetic")]
+    fn test_register_fn() {
+        let mut evaluator = Evaluator::default();
+        evaluator.register_pure_fn("mac", |a: Integer, b: Integer, c: Integer| a * b + c);
+        evaluator.register_fn("half", |a: Float| a / 2.0);
+        evaluator.register_fn("repeat", |s: String, n: Integer| {
+            usize::try_from(n)
+                .map(|n| s.repeat(n))
+                .map_err(|_| Error::Custom(format!("Invalid repeat count: {n}")))
+        });
+        evaluator.register_fn("type", |a: Operand| a.data_type().to_string());
+        evaluator.register_fn("answer", || 42 as Integer);
+
+        assert_eq!(
+            evaluator.eval("mac(2, 3, 4) + answer()").unwrap(),
+            Operand::Integer(52)
+        );
+        assert_eq!(evaluator.eval("half(3)").unwrap(), Operand::Float(1.5));
+        assert_eq!(
+            evaluator.eval("repeat('ab', 3)").unwrap(),
+            Operand::String("ababab".to_string())
+        );
+        assert_eq!(
+            evaluator.eval("type(1.0)").unwrap(),
+            Operand::String("Float".to_string())
+        );
+        assert_eq!(
+            evaluator.eval("repeat('ab', -1)"),
+            Err(Error::Custom("Invalid repeat count: -1".to_string()))
+        );
+        assert_eq!(
+            evaluator.eval("mac(2, 3.0, 4)"),
+            Err(Error::InvalidDataType {
+                expected: vec![OperandType::Integer],
+                got: OperandType::Float
+            })
+        );
+        assert_eq!(
+            evaluator.eval("mac(2, 3)").unwrap_err().to_string(),
+            "mac expects 3 arguments, got 2"
+        );
+
+        // functions with state are only folded if they declare themselves pure
+        struct Counter(std::sync::atomic::AtomicUsize);
+        impl Function for Counter {
+            fn name(&self) -> &str {
+                "counter"
+            }
+            fn signature(&self) -> Signature {
+                Signature::Fixed(0)
+            }
+            fn call(&self, _ctx: &EvalContext, _args: Vec<Operand>) -> Result<Operand, Error> {
+                let count = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok(Operand::Integer(count as Integer))
+            }
+        }
+        evaluator.register_function(Box::new(Counter(Default::default())));
+        let compiled = evaluator.compile("counter() + mac(2, 3, 4)").unwrap();
+        assert_eq!(compiled.expr().to_string(), "(counter() + 10)");
+        assert_eq!(
+            compiled.eval_with(&HashMap::new()).unwrap(),
+            Operand::Integer(10)
+        );
+        assert_eq!(
+            compiled.eval_with(&HashMap::new()).unwrap(),
+            Operand::Integer(11)
+        );
+
+        // closures may have state too, so they are only folded when registered as pure
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = count.clone();
+        evaluator.register_fn("next", move || {
+            counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed) as Integer
+        });
+        let compiled = evaluator.compile("next() * 2").unwrap();
+        assert_eq!(compiled.expr().to_string(), "(next() * 2)");
+        assert_eq!(
+            compiled.eval_with(&HashMap::new()).unwrap(),
+            Operand::Integer(0)
+        );
+        assert_eq!(
+            compiled.eval_with(&HashMap::new()).unwrap(),
+            Operand::Integer(2)
+        );
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "arithmetic")]
+    fn test_context() {
+        let mut evaluator = Evaluator::default();
+        evaluator.settings_mut().set_default_width(16).unwrap();
+        // invalid settings are rejected and leave the settings as they are
+        let settings = evaluator.settings_mut();
+        assert!(settings.set_default_width(129).is_err());
+        assert_eq!(settings.set_ibase(1), Err(Error::InvalidRadix(1)));
+        assert!(settings
+            .set_output(expr::context::OutputMode::Radix(37))
+            .is_err());
+        assert_eq!(evaluator.settings(), &{
+            let mut settings = expr::context::Settings::default();
+            settings.set_default_width(16).unwrap();
+            settings
+        });
+        evaluator.insert_function_handler(
+            "width",
+            Signature::Fixed(0),
+            Box::new(|ctx, _| Ok(Operand::Integer(ctx.settings().default_width() as Integer))),
+        );
+        evaluator.insert_function_handler(
+            "var",
+            Signature::Fixed(1),
+            Box::new(|ctx, args| {
+                let name = args[0].as_string()?;
+                ctx.variable(name)
+                    .cloned()
+                    .ok_or_else(|| Error::UnboundVariable(name.to_string()))
+            }),
+        );
+        evaluator.insert_function_handler(
+            "rev_ascii",
+            Signature::Fixed(1),
+            Box::new(|ctx, args| {
+                let reversed = ctx.call("rev", args)?;
+                ctx.call("ascii", vec![reversed])
+            }),
+        );
+        let mut total = 0;
+        evaluator.insert_handler(
+            Operator::Custom("acc".to_string()),
+            Box::new(move |_, args| {
+                for arg in args {
+                    total += arg.as_int()?;
+                }
+                Ok(Operand::Integer(total))
+            }),
+        );
+
+        assert_eq!(evaluator.eval("width()").unwrap(), Operand::Integer(16));
+        assert_eq!(
+            evaluator.eval("rev_ascii('abc')").unwrap(),
+            Operand::Integer(0x636261)
+        );
+        let compiled = evaluator.compile("acc(1, 2) + var('x')").unwrap();
+        let bindings = HashMap::from([("x".to_string(), Operand::Integer(100))]);
+        assert_eq!(
+            compiled.eval_with(&bindings).unwrap(),
+            Operand::Integer(103)
+        );
+        assert_eq!(
+            compiled.eval_with(&bindings).unwrap(),
+            Operand::Integer(106)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "arithmetic")]
+    fn test_threads() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Evaluator>();
+        assert_send_sync::<CompiledExpr>();
+
+        static EVALUATOR: std::sync::LazyLock<Evaluator> = std::sync::LazyLock::new(|| {
+            let mut evaluator = Evaluator::default();
+            let mut count = 0;
+            evaluator.insert_function_handler(
+                "next",
+                Signature::Fixed(0),
+                Box::new(move |_, _| {
+                    count += 1;
+                    Ok(Operand::Integer(count))
+                }),
+            );
+            evaluator
+        });
+        let compiled = Arc::new(EVALUATOR.compile("x * 2 + offset").unwrap());
+
+        let handles = (0..8)
+            .map(|i| {
+                let compiled = compiled.clone();
+                std::thread::spawn(move || {
+                    let mut evaluator = EVALUATOR.clone();
+                    evaluator.set_variable("offset", Operand::Integer(i));
+                    for x in 0..100 {
+                        let bindings = HashMap::from([
+                            ("x".to_string(), Operand::Integer(x)),
+                            ("offset".to_string(), Operand::Integer(i)),
+                        ]);
+                        assert_eq!(
+                            compiled.eval_with(&bindings).unwrap(),
+                            Operand::Integer(x * 2 + i)
+                        );
+                        assert_eq!(
+                            evaluator.eval("rev(0x1234) + offset").unwrap(),
+                            Operand::Integer(0x3412 + i)
+                        );
+                        evaluator.eval("next()").unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(EVALUATOR.eval("next()").unwrap(), Operand::Integer(801));
+        assert_eq!(
+            EVALUATOR.eval("offset"),
+            Err(Error::UnboundVariable("offset".to_string()))
+        );
+
+        // stateless handlers are not serialized, both calls wait for each other
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let mut evaluator = Evaluator::default();
+        evaluator.insert_op_handler(
+            Operator::Custom("sync".to_string()),
+            Box::new(move |_| {
+                barrier.wait();
+                Ok(())
+            }),
+        );
+        let evaluator = Arc::new(evaluator);
+        let handles = (0..2)
+            .map(|_| {
+                let evaluator = evaluator.clone();
+                std::thread::spawn(move || evaluator.eval("sync(1)").unwrap())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Operand::Integer(1));
+        }
+
+        // a panicking handler can be called again
+        let mut evaluator = Evaluator::default();
+        evaluator.insert_function_handler(
+            "check",
+            Signature::Fixed(1),
+            Box::new(|_, args| match &args[0] {
+                Operand::Integer(0) => panic!("check failed"),
+                arg => Ok(arg.clone()),
+            }),
+        );
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| evaluator.eval("check(0)")));
+        assert!(result.is_err());
+        assert_eq!(evaluator.eval("check(1)").unwrap(), Operand::Integer(1));
+    }
+
+    #[test]
+    fn test_json() {
+        let evaluator = Evaluator::default();
+        let json = serde_json::to_value(evaluator.eval("0x4142").unwrap()).unwrap();
+        assert_eq!(json["type"], "Integer");
+        assert_eq!(json["decimal"], "16706");
+        assert_eq!(json["hex"], "0x4142");
+        assert_eq!(json["octal"], "0o40502");
+        assert_eq!(json["binary"], "0b100000101000010");
+        assert_eq!(json["bits"], serde_json::json!([1, 6, 8, 14]));
+        assert_eq!(json["ascii"]["big_endian"], "AB");
+        assert_eq!(json["ascii"]["little_endian"], "BA");
+        assert_eq!(json["float"], 16706.0);
+        let json = serde_json::to_value(Operand::Integer(0xAB)).unwrap();
+        assert_eq!(json["hex"], "0xAB");
+        assert_eq!(json["ascii"]["big_endian"], serde_json::Value::Null);
+        let json = serde_json::to_value(Operand::Integer(0)).unwrap();
+        assert_eq!(json["hex"], "0x0");
+        assert_eq!(json["ascii"]["big_endian"], serde_json::Value::Null);
+        assert_eq!(json["ascii"]["little_endian"], serde_json::Value::Null);
+        let json = serde_json::to_value(Operand::Integer(-1)).unwrap();
+        assert_eq!(json["hex"], format!("0x{}", "F".repeat(32)));
+
+        let json = serde_json::to_value(evaluator.eval("1 + $").unwrap_err()).unwrap();
+        assert_eq!(json["kind"], "InvalidToken");
+        assert_eq!(json["span"], serde_json::json!({ "start": 4, "end": 5 }));
+        for (formula, kind, span) in [
+            ("1 + )", "ExpectOperand", Some(4..5)),
+            ("(1 + 2", "UnexpectedEnd", Some(6..6)),
+            ("1 2", "ExpectOperator", Some(2..3)),
+            ("1 as u0", "Custom", Some(5..7)),
+            ("y", "UnboundVariable", None),
+        ] {
+            let error = evaluator.eval(formula).unwrap_err();
+            assert_eq!((error.kind(), error.span()), (kind, span), "{formula}");
+        }
+        let evaluator = Evaluator::builder().dialect(Dialect::C).build().unwrap();
+        let error = evaluator.eval("1 ? 2 3").unwrap_err();
+        assert_eq!((error.kind(), error.span()), ("ExpectToken", Some(6..7)));
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "arithmetic",
+        feature = "bitwise",
+        feature = "strings",
+        feature = "math",
+        feature = "firmware"
+    ))]
+    fn test_builder() {
+        use builder::EvaluatorBuilder;
+        use expr::{
+            builtin::BuiltinSet,
+            context::{DivisionMode, OverflowPolicy},
+        };
+
+        let evaluator = Evaluator::default();
+        assert_eq!(
+            evaluator.eval("1 << 127 << 0").unwrap(),
+            Operand::Integer(Integer::MIN)
+        );
+        assert_eq!(
+            evaluator.eval("(1 << 126) * 2").unwrap_err(),
+            Error::Overflow
+        );
+        assert_eq!(evaluator.eval("1 / 0").unwrap_err(), Error::DivisionByZero);
+        assert_eq!(evaluator.eval("-7 / 2").unwrap(), Operand::Integer(-3));
+        assert_eq!(evaluator.eval("-7 % 2").unwrap(), Operand::Integer(-1));
+        assert_eq!(evaluator.eval("~0").unwrap(), Operand::Integer(0xFFFFFFFF));
+
+        let evaluator = Evaluator::builder()
+            .default_width(8)
+            .overflow(OverflowPolicy::Saturate)
+            .division(DivisionMode::Floor)
+            .build()
+            .unwrap();
+        assert_eq!(evaluator.eval("~0").unwrap(), Operand::Integer(0xFF));
+        assert_eq!(
+            evaluator.eval("0x1234 ^ 0xFF").unwrap(),
+            Operand::Integer(0xCB)
+        );
+        assert_eq!(evaluator.eval("w~0").unwrap(), Operand::Integer(0xFFFF));
+        assert_eq!(
+            evaluator.eval("(1 << 126) * 2").unwrap(),
+            Operand::Integer(Integer::MAX)
+        );
+        assert_eq!(evaluator.eval("-7 / 2").unwrap(), Operand::Integer(-4));
+        assert_eq!(evaluator.eval("-7 % 2").unwrap(), Operand::Integer(1));
+
+        let evaluator = Evaluator::builder()
+            .overflow(OverflowPolicy::Wrap)
+            .build()
+            .unwrap();
+        assert_eq!(
+            evaluator.eval("(1 << 126) * 2").unwrap(),
+            Operand::Integer(Integer::MIN)
+        );
+
+        let evaluator = EvaluatorBuilder::empty()
+            .with_set(BuiltinSet::Arithmetic)
+            .with_set(BuiltinSet::Math)
+            .build()
+            .unwrap();
+        assert_eq!(
+            evaluator.eval("max(1, 2.5) + abs(-2)").unwrap(),
+            Operand::Float(4.5)
+        );
+        assert_eq!(
+            evaluator.eval("round(pi * 100)").unwrap(),
+            Operand::Integer(314)
+        );
+        assert!(matches!(
+            evaluator.eval("1 | 2").unwrap_err(),
+            Error::NoMatchingHandler { .. }
+        ));
+        assert!(evaluator.function("rev").is_none());
+
+        let evaluator = Evaluator::builder()
+            .without_set(BuiltinSet::Strings)
+            .without_set(BuiltinSet::Bytes)
+            .build()
+            .unwrap();
+        assert!(evaluator.function("base").is_none());
+        assert!(evaluator.function("ascii").is_none());
+        assert_eq!(
+            evaluator
+                .eval("field(align_up(0x1234, 256), 15, 8)")
+                .unwrap(),
+            Operand::Integer(0x13)
+        );
+        assert_eq!(
+            evaluator.eval("genmask(3, 0)").unwrap(),
+            Operand::Integer(0xF)
+        );
+
+        // the first invalid setting is returned by build
+        assert_eq!(
+            Evaluator::builder()
+                .ibase(1)
+                .default_width(129)
+                .build()
+                .err(),
+            Some(Error::InvalidRadix(1))
+        );
+        assert!(Evaluator::builder()
+            .output(expr::context::OutputMode::Radix(37))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_infix() {
+        let mut evaluator = Evaluator::default();
+        evaluator.register_pure_fn("cmp", |a: Integer, b: Integer| a.cmp(&b) as Integer);
+        evaluator.register_pure_fn("sub", |a: Integer, b: Integer| a - b);
+        evaluator
+            .register_infix("<=>", "cmp", 9, Associativity::Left)
+            .unwrap();
+        evaluator
+            .register_infix("minus", "sub", 4, Associativity::Right)
+            .unwrap();
+        evaluator
+            .register_infix("**", "sub", 1, Associativity::Left)
+            .unwrap();
+        evaluator
+            .register_infix("<", "cmp", 9, Associativity::Left)
+            .unwrap();
+
+        assert_eq!(evaluator.eval("2 <=> 1 | 2").unwrap(), Operand::Integer(-1));
+        assert_eq!(
+            evaluator.eval("5 minus 3 minus 1").unwrap(),
+            Operand::Integer(3)
+        );
+        assert_eq!(evaluator.eval("2 * 5 ** 3").unwrap(), Operand::Integer(4));
+        assert_eq!(evaluator.eval("1 << 2 < 3").unwrap(), Operand::Integer(1));
+        assert_eq!(
+            evaluator.eval("minus + 1").unwrap_err(),
+            Error::UnboundVariable("minus".to_string())
+        );
+        let compiled = evaluator.compile("x <=> 1 ** 2").unwrap();
+        assert_eq!(compiled.expr().to_string(), "(x <=> (-1))");
+        assert_eq!(
+            evaluator
+                .parse(&compiled.expr().to_string())
+                .unwrap()
+                .to_string(),
+            "(x <=> -1)"
+        );
+
+        for symbol in ["<<", "^^", "b^", "bit3", "(", ".", "a+", "", "1"] {
+            assert!(
+                matches!(
+                    evaluator.register_infix(symbol, "cmp", 9, Associativity::Left),
+                    Err(Error::InvalidOperator { .. })
+                ),
+                "{symbol}"
+            );
+        }
+        assert!(evaluator
+            .register_infix("<=>", "cmp", 0, Associativity::Left)
+            .is_err());
+
+        evaluator
+            .load_config(
+                "# comment\n\nfn avg(a, b) = (a + b) / 2\ninfix <+> avg 4 left\nfn loop(a) = loop(a)",
+            )
+            .unwrap();
+        assert_eq!(evaluator.eval("2 <+> 4 * 2").unwrap(), Operand::Integer(5));
+        assert!(evaluator.eval("loop(1)").is_err());
+        assert_eq!(
+            evaluator
+                .load_config("infix <+> avg 4 up")
+                .unwrap_err()
+                .kind(),
+            "Config"
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_keywords() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("1 shl 12 or 0x3"), Operand::Integer(0x1003));
+        assert_eq!(eval("0xFF and not 0xF"), Operand::Integer(0xF0));
+        assert_eq!(eval("0xFF xor 0x0F shr 2"), Operand::Integer(0xFC));
+        assert_eq!(eval("7 mod 4 x 3"), Operand::Integer(7 % 4 * 3));
+        assert_eq!(eval("2 pow 3 times [1 + 1]"), Operand::Integer(16));
+        assert_eq!(eval("[2 x [3 - 1]] x 2"), Operand::Integer(8));
+        assert!(evaluator.eval("[1 + 2)").is_err());
+
+        let mut evaluator = Evaluator::default();
+        evaluator.set_variable("x", Operand::Integer(3));
+        assert_eq!(evaluator.eval("x x x").unwrap(), Operand::Integer(9));
+        assert_eq!(
+            evaluator.eval("andx + 1").unwrap_err(),
+            Error::UnboundVariable("andx".to_string())
+        );
+        for symbol in ["x", "and", "shl"] {
+            assert!(evaluator
+                .register_infix(symbol, "f", 4, Associativity::Left)
+                .is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_input_radix() {
+        let mut evaluator = Evaluator::default();
+        let radix = |formula: &str| evaluator.input_radix(formula).unwrap();
+        assert_eq!(radix("0x1000 + 0x200"), Some(16));
+        assert_eq!(radix("0x1000 << 4"), Some(16));
+        assert_eq!(radix("0b1 | 0b10 | 0x4"), Some(2));
+        assert_eq!(radix("0b1 | 0o7 | 0x4"), Some(16));
+        assert_eq!(radix("0b1 | 0o7"), Some(2));
+        assert_eq!(radix("0o1 + 0o7 + 0x1"), Some(8));
+        assert_eq!(radix("1k + 2"), Some(10));
+        assert_eq!(radix("1.5 + pi"), None);
+        assert!(evaluator.input_radix("0x1 $").is_err());
+
+        evaluator
+            .load_config("set output auto\nset width 16\nset division float")
+            .unwrap();
+        assert_eq!(
+            evaluator.settings().output(),
+            expr::context::OutputMode::Auto
+        );
+        assert_eq!(evaluator.eval("~0").unwrap(), Operand::Integer(0xFFFF));
+        assert_eq!(evaluator.eval("1 / 2").unwrap(), Operand::Float(0.5));
+        assert!(evaluator.load_config("set width 129").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "strings"))]
+    fn test_radix() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("36#ZZ + 36#zz"), Operand::Integer(2 * 1295));
+        assert_eq!(eval("3#210 + 16#ff"), Operand::Integer(21 + 255));
+        assert_eq!(
+            eval("base(255, 2)"),
+            Operand::String("11111111".to_string())
+        );
+        assert_eq!(eval("parse(base(-12345, 7), 7)"), Operand::Integer(-12345));
+        assert_eq!(evaluator.eval("37#1").unwrap_err().kind(), "InvalidRadix");
+        assert_eq!(
+            evaluator.eval("base(1, 1)").unwrap_err(),
+            Error::InvalidRadix(1)
+        );
+        assert_eq!(evaluator.input_radix("36#ZZ + 1").unwrap(), Some(36));
+
+        let evaluator = Evaluator::builder().ibase(16).build().unwrap();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("DEADBEEF"), Operand::Integer(0xDEADBEEF));
+        assert_eq!(eval("1F + 10"), Operand::Integer(0x1F + 0x10));
+        assert_eq!(eval("1E5"), Operand::Integer(0x1E5));
+        assert_eq!(eval("0b11 + 10#10"), Operand::Integer(13));
+        assert_eq!(eval("rev(0A0B)"), Operand::Integer(0x0B0A));
+        assert_eq!(eval("1.5 * 2"), Operand::Float(3.0));
+        assert!(evaluator.eval("GG").is_err());
+        assert_eq!(evaluator.input_radix("FF + 1").unwrap(), Some(16));
+
+        let evaluator = Evaluator::builder().ibase(8).build().unwrap();
+        assert_eq!(evaluator.eval("17").unwrap(), Operand::Integer(0o17));
+        assert_eq!(evaluator.eval("19").unwrap_err().kind(), "ParseIntError");
+    }
+
+    #[test]
+    #[cfg(feature = "bitwise")]
+    fn test_infer_width() {
+        let evaluator = Evaluator::builder().infer_width(true).build().unwrap();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("~0x00FF"), Operand::Integer(0xFF00));
+        assert_eq!(eval("~0b0101"), Operand::Integer(0b1010));
+        assert_eq!(eval("~0x0_F"), Operand::Integer(0xF0));
+        assert_eq!(eval("0x00FF ^ 0x0F"), Operand::Integer(0xF0));
+        assert_eq!(eval("~0x0Fu ^ 1"), Operand::Integer(0xF1));
+        assert_eq!(eval("~0FFh"), Operand::Integer(0xF00));
+        assert_eq!(eval("~16#0F"), Operand::Integer(0xF0));
+        assert_eq!(eval("~1"), Operand::Integer(0xFFFF_FFFE));
+        assert_eq!(eval("~0o17"), Operand::Integer(0xFFFF_FFF0));
+        assert_eq!(eval("b~0x0F"), Operand::Integer(0xF0));
+        assert_eq!(evaluator.input_width("0x00FF + 0b1").unwrap(), Some(16));
+        assert_eq!(
+            Evaluator::default().eval("~0x00FF").unwrap(),
+            Operand::Integer(0xFFFF_FF00)
+        );
+
+        let evaluator = Evaluator::builder()
+            .infer_width(true)
+            .ibase(16)
+            .build()
+            .unwrap();
+        assert_eq!(evaluator.eval("~00FF").unwrap(), Operand::Integer(0xFF00));
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_width_hints() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("{24}~0"), Operand::Integer(0xFF_FFFF));
+        assert_eq!(eval("u12~0x0F"), Operand::Integer(0xFF0));
+        assert_eq!(eval("ll!0"), Operand::Integer(-1));
+        assert_eq!(eval("0xF0 u4^ 0xFF"), Operand::Integer(0xF));
+        assert_eq!(eval("{8}-1"), Operand::Integer(0xFF));
+        assert_eq!(eval("0xFFF {12}<< 4"), Operand::Integer(0xFF0));
+        assert_eq!(eval("0xFFF u8>>> 4"), Operand::Integer(0xF));
+        assert_eq!(eval("0xFFF u8>> 4"), Operand::Integer(0xFF));
+        assert_eq!(eval("0x81 {8}rol 1"), Operand::Integer(0x03));
+        assert_eq!(eval("0x81 {8}ror 1"), Operand::Integer(0xC0));
+        assert_eq!(eval("0x81 {8}rol 9"), Operand::Integer(0x03));
+        assert_eq!(eval("0x80000001 rol 4"), Operand::Integer(0x18));
+        assert_eq!(eval("12'h801 rol 1"), Operand::Integer(0x003));
+        assert_eq!(eval("1 {128}ror 1"), Operand::Integer(Integer::MIN));
+        assert_eq!(
+            evaluator.eval("{0}~1").unwrap_err(),
+            Error::Spanned {
+                error: Box::new(Error::InvalidBitWidthHint("{0}".to_string())),
+                span: 0..4
+            }
+        );
+        assert!(evaluator.eval("u129^1").is_err());
+        assert!(evaluator.eval("1 {8}<< -1").is_err());
+
+        // hints survive printing and parsing back
+        for formula in ["{24}~x", "x {12}>> 2", "{8}-x", "x {20}rol 3", "x b^ 1"] {
+            let expr = evaluator.parse(formula).unwrap();
+            assert_eq!(evaluator.parse(&expr.to_string()).unwrap(), expr);
+        }
+
+        let evaluator = Evaluator::builder().default_width(12).build().unwrap();
+        assert_eq!(evaluator.eval("~0").unwrap(), Operand::Integer(0xFFF));
+        assert_eq!(evaluator.eval("0x801 rol 1").unwrap(), Operand::Integer(3));
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise", feature = "math"))]
+    fn test_width_arithmetic() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("0xFFFF w+ 1"), Operand::Integer(0));
+        assert_eq!(eval("0 w- 1"), Operand::Integer(0xFFFF));
+        assert_eq!(eval("0x8000_0001 dw* 2"), Operand::Integer(2));
+        assert_eq!(eval("200 {8}+ 100"), Operand::Integer(44));
+        assert_eq!(eval("1 dw<< 32"), Operand::Integer(0));
+        assert_eq!(eval("-8 dw>>> 1"), Operand::Integer(0x7FFF_FFFC));
+        assert_eq!(eval("-8 dw>> 1"), Operand::Integer(0xFFFF_FFFC));
+        assert_eq!(eval("0x80 b>> 1"), Operand::Integer(0xC0));
+        assert_eq!(eval("0x40 b>> 1"), Operand::Integer(0x20));
+        assert_eq!(eval("0x80 b>> 200"), Operand::Integer(0xFF));
+        assert_eq!(eval("-1 >>> 120"), Operand::Integer(0xFF));
+        assert_eq!(eval("-8 >> 1"), Operand::Integer(-4));
+        assert_eq!(eval("1 << 127"), Operand::Integer(Integer::MIN));
+        for formula in ["1 << 128", "1 << -1", "1 >> 200", "1 >> -1"] {
+            assert!(evaluator.eval(formula).is_err(), "{formula}");
+        }
+        assert_eq!(eval("wrap_add(0xFFFF, 2, 16)"), Operand::Integer(1));
+        assert_eq!(eval("wrap_sub(0, 1, 8)"), Operand::Integer(0xFF));
+        assert_eq!(eval("sat_add(250, 10, 8)"), Operand::Integer(0xFF));
+        assert_eq!(eval("sat_sub(5, 10, 8)"), Operand::Integer(0));
+        assert_eq!(eval("sat_mul(-3, 2, 8)"), Operand::Integer(0));
+        assert!(evaluator.eval("sat_add(1, 1, 0)").is_err());
+        assert!(evaluator.eval("1.5 w+ 1").is_err());
+
+        // letter hints in operand position are variables
+        let mut evaluator = Evaluator::default();
+        evaluator.set_variable("b", Operand::Integer(3));
+        evaluator.set_variable("l", Operand::Integer(5));
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("b+1"), Operand::Integer(4));
+        assert_eq!(eval("(l-1) * b"), Operand::Integer(12));
+        assert_eq!(eval("max(1, b*2)"), Operand::Integer(6));
+        assert_eq!(eval("b^1"), Operand::Integer(2));
+        assert_eq!(eval("b w+ l"), Operand::Integer(8));
+        assert_eq!(
+            evaluator.eval("10l+1").unwrap_err().kind(),
+            "AmbiguousLiteral"
+        );
+
+        for formula in ["x w+ 1", "x {12}- 1", "x dw>>> 2", "x l>> 2", "b b* 2"] {
+            let expr = evaluator.parse(formula).unwrap();
+            assert_eq!(evaluator.parse(&expr.to_string()).unwrap(), expr);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_casts() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("(u8)0x1234"), Operand::Integer(0x34));
+        assert_eq!(eval("(i16)0x8000"), Operand::Integer(-0x8000));
+        assert_eq!(eval("0xFF as i8"), Operand::Integer(-1));
+        assert_eq!(eval("-1 as u4"), Operand::Integer(15));
+        assert_eq!(eval("-(1 as u4)"), Operand::Integer(-1));
+        assert_eq!(eval("~1 as u4"), Operand::Integer(14));
+        assert_eq!(eval("(u8)-1 as i4"), Operand::Integer(-1));
+        assert_eq!(eval("0x1FF as u8+1"), Operand::Integer(0x100));
+        assert_eq!(eval("0x1FF as u8<<1"), Operand::Integer(0x1FE));
+        assert_eq!(eval("0x1FF as u8*2-1"), Operand::Integer(0x1FD));
+        assert_eq!(eval("0x1FF u8+ 1"), Operand::Integer(0));
+        assert_eq!(eval("2 * 300 as u8"), Operand::Integer(88));
+        assert_eq!(eval("~(u8)5"), Operand::Integer(0xFA));
+        assert_eq!(eval("(u12)'1"), Operand::Integer(0xFFF));
+        assert_eq!(eval("(float)7 / 2"), Operand::Float(3.5));
+        assert_eq!(eval("3.7 as u8 round"), Operand::Integer(4));
+        assert_eq!(eval("3.7 as integer trunc"), Operand::Integer(3));
+        assert_eq!(eval("(i8 floor)-2.5"), Operand::Integer(-3));
+        assert_eq!(eval("(-2.5) as i8 ceil"), Operand::Integer(-2));
+        assert_eq!(eval("300.5 as u8 floor"), Operand::Integer(44));
+        assert_eq!(eval("'0x1F' as u4"), Operand::Integer(0xF));
+        assert_eq!(eval("'-3.5' as float"), Operand::Float(-3.5));
+        assert_eq!(eval("'2.5' as integer round"), Operand::Integer(3));
+        assert_eq!(eval("(string)42"), Operand::String("42".to_string()));
+        assert_eq!(eval("1.5 as string"), Operand::String("1.5".to_string()));
+        assert!(evaluator.eval("3.7 as u8").is_err());
+        assert!(evaluator.eval("1.5 as float round").is_err());
+        assert!(evaluator.eval("'abc' as integer").is_err());
+        assert!(evaluator.eval("1e40 as integer round").is_err());
+        assert!(evaluator.eval("1 as u0").is_err());
+
+        // parenthesized variables are not casts
+        let mut evaluator = Evaluator::default();
+        evaluator.set_variable("u8", Operand::Integer(3));
+        assert_eq!(evaluator.eval("(u8) * 2").unwrap(), Operand::Integer(6));
+
+        for formula in [
+            "(u8)x",
+            "(i12 round)x + 1",
+            "x as float",
+            "-(x as u8) as i4",
+            "-x as u8",
+        ] {
+            let expr = evaluator.parse(formula).unwrap();
+            assert_eq!(evaluator.parse(&expr.to_string()).unwrap(), expr);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_c_dialect() {
+        use expr::context::DivisionMode;
+
+        let evaluator = Evaluator::builder().dialect(Dialect::C).build().unwrap();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("((1U << 5) | (3 << 8))"), Operand::Integer(0x320));
+        assert_eq!(eval("5 ^ 3"), Operand::Integer(6));
+        assert_eq!(eval("~0"), Operand::Integer(-1));
+        assert_eq!(eval("~0u"), Operand::Integer(0xFFFF_FFFF));
+        assert_eq!(eval("~(unsigned short)0"), Operand::Integer(-1));
+        assert_eq!(eval("!5"), Operand::Integer(0));
+        assert_eq!(eval("!0 + 1"), Operand::Integer(2));
+        assert_eq!(eval("1 << 31"), Operand::Integer(-0x8000_0000));
+        assert_eq!(eval("1u << 31"), Operand::Integer(0x8000_0000));
+        assert_eq!(eval("0x7FFFFFFF + 1"), Operand::Integer(-0x8000_0000));
+        assert_eq!(eval("0xFFFFFFFF + 1"), Operand::Integer(0));
+        assert_eq!(eval("-1 ^ 0U"), Operand::Integer(0xFFFF_FFFF));
+        assert_eq!(eval("-1U / 2"), Operand::Integer(0x7FFF_FFFF));
+        assert_eq!(eval("-1 >> 4"), Operand::Integer(-1));
+        assert_eq!(eval("0x80000000 >> 4"), Operand::Integer(0x0800_0000));
+        assert_eq!(eval("1ul<<40"), Operand::Integer(1 << 40));
+        assert_eq!(eval("(long)1 << 40"), Operand::Integer(1 << 40));
+        assert_eq!(eval("-7 / 2"), Operand::Integer(-3));
+        assert_eq!(eval("7 / 2.0"), Operand::Float(3.5));
+        assert_eq!(eval("(int)-2.7"), Operand::Integer(-2));
+        assert_eq!(eval("(uint8_t)0x1FF"), Operand::Integer(0xFF));
+        assert_eq!(eval("1 + 2 * 3 & 4"), Operand::Integer(4));
+        assert_eq!(eval("010"), Operand::Integer(8));
+        assert_eq!(eval("'A' + 1"), Operand::Integer(66));
+        assert!(evaluator.eval("2 ^^ 3").is_err());
+        assert!(evaluator.eval("1 w+ 1").is_err());
+        assert!(evaluator.eval("09").is_err());
+
+        let evaluator = Evaluator::builder()
+            .dialect(Dialect::C)
+            .division(DivisionMode::Float)
+            .build()
+            .unwrap();
+        assert_eq!(evaluator.eval("7 / 2").unwrap(), Operand::Integer(3));
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_c_conditions() {
+        let evaluator = Evaluator::builder().dialect(Dialect::C).build().unwrap();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("1 == 1"), Operand::Integer(1));
+        assert_eq!(eval("1 != 1"), Operand::Integer(0));
+        assert_eq!(eval("1 < 2"), Operand::Integer(1));
+        assert_eq!(eval("2 <= 1"), Operand::Integer(0));
+        assert_eq!(eval("2.5 > 2"), Operand::Integer(1));
+        assert_eq!(eval("2 >= 2"), Operand::Integer(1));
+        assert_eq!(eval("-1 < 1u"), Operand::Integer(0));
+        assert_eq!(eval("-1 < 1"), Operand::Integer(1));
+        assert_eq!(eval("1 << 2 < 5"), Operand::Integer(1));
+        assert_eq!(eval("3 > 2 == 1"), Operand::Integer(1));
+        assert_eq!(eval("2 & 3 == 3"), Operand::Integer(0));
+        assert_eq!(eval("2 && 3"), Operand::Integer(1));
+        assert_eq!(eval("2 && 0.0"), Operand::Integer(0));
+        assert_eq!(eval("0 || 0"), Operand::Integer(0));
+        assert_eq!(eval("0 || 5"), Operand::Integer(1));
+        assert_eq!(eval("1 || 0 && 0"), Operand::Integer(1));
+        assert_eq!(eval("0 && 1 / 0"), Operand::Integer(0));
+        assert_eq!(eval("1 || 1 / 0"), Operand::Integer(1));
+        assert_eq!(eval("1 ? 2 : 3"), Operand::Integer(2));
+        assert_eq!(eval("0 ? 2 : 0 ? 3 : 4"), Operand::Integer(4));
+        assert_eq!(eval("1 | 2 ? 1 + 1 : 1 / 0"), Operand::Integer(2));
+        assert_eq!(eval("0 ? 1u : -1"), Operand::Integer(0xFFFF_FFFF));
+        assert_eq!(eval("(1 ? 1 : 2.0) / 2"), Operand::Float(0.5));
+        assert_eq!(
+            evaluator.eval("1 ? 2").unwrap_err().without_span(),
+            &Error::UnexpectedEnd
+        );
+        assert!(evaluator.eval("1 : 2").is_err());
+
+        let compiled = evaluator.compile("1 < 2 ? x : y").unwrap();
+        assert_eq!(compiled.expr(), &Expr::Variable("x".to_string()));
+        assert_eq!(compiled.variables(), ["x"]);
+        let compiled = evaluator.compile("x && 2 > 1").unwrap();
+        assert_eq!(compiled.expr().to_string(), "(x ? 1 : 0)");
+
+        // rcal has no comparisons, so registered infix operators can still use their symbols
+        let mut evaluator = Evaluator::default();
+        assert!(evaluator.eval("1 == 1").is_err());
+        evaluator.register_fn("eq", |a: Integer, b: Integer| (a == b) as Integer);
+        evaluator
+            .register_infix("==", "eq", 9, Associativity::Left)
+            .unwrap();
+        assert_eq!(evaluator.eval("1 + 1 == 2").unwrap(), Operand::Integer(1));
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "math"))]
+    fn test_implicit_mul() {
+        let mut evaluator = Evaluator::builder().implicit_mul(true).build().unwrap();
+        evaluator.set_variable("x", Operand::Integer(3));
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("2(3+4)"), Operand::Integer(14));
+        assert_eq!(eval("4k(1+x)"), Operand::Integer(0x4000));
+        assert_eq!(eval("3pi"), eval("3 * pi"));
+        assert_eq!(eval("(1+2)(3+4)"), Operand::Integer(21));
+        assert_eq!(eval("2x + 1"), Operand::Integer(7));
+        assert_eq!(eval("-2x"), Operand::Integer(-6));
+        assert_eq!(eval("2 ^^ 3x"), Operand::Integer(24));
+        assert_eq!(eval("12 / 2x"), Operand::Integer(18));
+        assert_eq!(eval("2 3"), Operand::Integer(6));
+        assert_eq!(eval("2bit3"), Operand::Integer(16));
+        assert_eq!(eval("1bit3"), Operand::Integer(8));
+        assert_eq!(eval("2 max(x, 4)"), Operand::Integer(8));
+        assert_eq!(eval("2 - 3"), Operand::Integer(-1));
+        assert_eq!(eval("4k"), Operand::Integer(4096));
+        assert_eq!(eval("3 x as u1"), Operand::Integer(3));
+        for formula in ["4kx", "3kg", "2w+1", "1.5.5", "0xFFpi", "2 x 3"] {
+            assert_eq!(
+                evaluator.eval(formula).unwrap_err().kind(),
+                "AmbiguousJuxtaposition",
+                "{formula}"
+            );
+        }
+        assert!(Evaluator::default().eval("2(3+4)").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "arithmetic", feature = "bitwise"))]
+    fn test_verilog() {
+        let evaluator = Evaluator::default();
+        let eval = |formula: &str| evaluator.eval(formula).unwrap();
+        assert_eq!(eval("~8'h0F"), Operand::Integer(0xF0));
+        assert_eq!(eval("~12'd0"), Operand::Integer(0xFFF));
+        assert_eq!(eval("4'b1010 ^ 4'b0110"), Operand::Integer(0b1100));
+        assert_eq!(eval("8'h0F ^ ~16'h0"), Operand::Integer(0xFFF0));
+        assert_eq!(eval("12'hFFF & ~12'h00F"), Operand::Integer(0xFF0));
+        assert_eq!(eval("w~8'h0F"), Operand::Integer(0xFFF0));
+        assert_eq!(eval("8'hF0 ^ '1"), Operand::Integer(0x0F));
+        assert_eq!(eval("16'h00FF | '1"), Operand::Integer(0xFFFF));
+        assert_eq!(eval("~'0"), Operand::Integer(0xFFFF_FFFF));
+        assert_eq!(eval("'1 & 8'hF0 + 1"), Operand::Integer(0xF1));
+        assert_eq!(eval("8'sh80 + 1"), Operand::Integer(-127));
+        assert_eq!(
+            eval("~'0"),
+            Evaluator::builder()
+                .default_width(32)
+                .build()
+                .unwrap()
+                .eval("'1")
+                .unwrap()
+        );
+        assert_eq!(
+            Evaluator::builder()
+                .default_width(8)
+                .build()
+                .unwrap()
+                .eval("'1")
+                .unwrap(),
+            Operand::Integer(0xFF)
+        );
+
+        assert_eq!(evaluator.input_width("4'h1 + 16'h1").unwrap(), Some(16));
+        assert!(evaluator.warnings("8'hFF").unwrap().is_empty());
+        assert_eq!(
+            evaluator.warnings("8'h1FF + 1").unwrap(),
+            vec!["sized literal does not fit into 8 bits, truncated to 0xFF".to_string()]
+        );
+    }
 }
@@ -1,6 +1,13 @@
 use anyhow::Context;
-use rcal::{expr::operand::Operand, Evaluator};
 use clap::{Args, Parser};
+use rcal::{
+    expr::{context::OutputMode, operand::Operand},
+    format::{
+        bit_ruler, fit_width, fits_width, set_bit_ranges, verilog, NumberFormat, Separator, Sign,
+    },
+    lex::Dialect,
+    Evaluator, Integer,
+};
 
 /// A cli calculator
 #[derive(Debug, Parser)]
@@ -16,15 +23,71 @@ struct Cli {
     expr: Vec<String>,
     #[arg(short, long, action = clap::ArgAction::Count)]
     pretty: u8,
+    /// Bit width used for two's complement representation
+    #[arg(long, short, value_parser = clap::value_parser!(u32).range(1..=128))]
+    width: Option<u32>,
+    /// Pad integer output with zeros to the full bit width of a register:
+    /// hex, octal and binary to its digits in two's complement,
+    /// other radixes to the digits of its largest unsigned value
+    #[arg(
+        long,
+        value_parser = parse_pad,
+        conflicts_with_all = ["exp", "verilog", "bits", "ruler", "json", "all"]
+    )]
+    pad: Option<u32>,
+    /// Truncate the result with a warning instead of an error
+    /// when it does not fit into the --pad width
+    #[arg(long, requires = "pad")]
+    truncate: bool,
+    /// Highlight set bits in --ruler output with ANSI colors
+    #[arg(long)]
+    color: bool,
+    /// List available functions
+    #[arg(long)]
+    list_functions: bool,
+    /// Show the documentation of a function
+    #[arg(long, value_name = "NAME")]
+    help_fn: Option<String>,
+    /// Read numbers without prefix in the radix, from 2 to 36.
+    /// Digits above 9 are upper case letters, like bc
+    #[arg(long, value_name = "N", value_parser = parse_radix)]
+    ibase: Option<u32>,
+    /// Give hexadecimal and binary literals the width of their digits, leading zeros included,
+    /// so `~0x00FF` is `0xFF00`. Hex, octal and binary output is padded to the widest literal
+    #[arg(long)]
+    infer_width: bool,
+    /// Operator syntax: `rcal`, or `c` for expressions copied from C,
+    /// with C precedence, integer types and promotion, `^` as XOR and `!` as logical NOT
+    #[arg(long, value_name = "DIALECT", value_parser = parse_dialect)]
+    dialect: Option<Dialect>,
+    /// Read an operand followed by `(`, an identifier or a number as a multiplication,
+    /// like `2(3+4)`, `4k(1+x)` and `3pi`
+    #[arg(long)]
+    implicit_mul: bool,
+    /// Load function and infix operator definitions from a file
+    #[arg(long, value_name = "FILE")]
+    config: Option<std::path::PathBuf>,
 }
 
 #[derive(Args, Debug)]
 #[group(required = false, multiple = false)]
 struct PrintFormat {
+    /// Output with decimal format, even if the config sets another output mode
+    #[arg(long, short)]
+    dec: bool,
+    /// Output integers in the radix most integer literals are written in.
+    /// Decimal literals only count if all the literals are decimal,
+    /// ties are broken in favor of hex, then binary and octal
+    #[arg(long)]
+    auto: bool,
     /// Output with hexadecimal format
     /// (the result of the expression must be integer)
     #[arg(long, short = 'x')]
     hex: bool,
+    /// Output in the radix, from 2 to 36
+    /// (the result of the expression must be integer)
+    #[arg(long, value_name = "N", value_parser = parse_radix)]
+    obase: Option<u32>,
     /// Output with octal format
     /// (the result of the expression must be integer)
     #[arg(long, short)]
@@ -36,92 +99,161 @@ struct PrintFormat {
     /// Print floating point number with scientific notation
     #[arg(long, short)]
     exp: bool,
+    /// Output as a Verilog sized literal in hexadecimal, like `32'hDEADBEEF`.
+    /// Sized to --width, the widest sized literal of the expression
+    /// or the smallest register that holds the result
+    #[arg(long)]
+    verilog: bool,
     /// Output list of 1 bits in the result
     /// (the result of the expression must be integer)
     #[arg(long)]
     bits: bool,
+    /// Draw the result as a bit ruler, sized to --width or the smallest register that holds it
+    /// (the result of the expression must be integer)
+    #[arg(long, short)]
+    ruler: bool,
+    /// Output every representation of the result in JSON format.
+    /// Errors are reported as JSON as well
+    #[arg(long)]
+    json: bool,
+    /// Output a summary of the result in every format
+    /// (the result of the expression must be integer)
+    #[arg(long, short)]
+    all: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
     let expr = args.expr.join(" ");
-    let evaluator = Evaluator::default();
-    let result = evaluator.eval(&expr)?;
-    if args.format.hex {
-        let num = result.as_int().with_context(|| {
-            format!(
-                "--hex flag requires the result of the expression to be integer. Got {result:?}",
-            )
-        })?;
-        if args.pretty == 0 {
-            println!("{num:X}");
-        } else if args.pretty == 1 {
-            println!("{num:#X}");
-        } else {
-            let num_str = format!("{num:X}");
-            let mut str_sections = vec![];
-            if num_str.len() % 4 != 0 {
-                str_sections.push(&num_str[0..num_str.len() % 4]);
+    let mut evaluator = Evaluator::default();
+    if let Some(path) = &args.config {
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        evaluator
+            .load_config(&config)
+            .with_context(|| format!("Failed to load {}", path.display()))?;
+    }
+    if let Some(ibase) = args.ibase {
+        evaluator.settings_mut().set_ibase(ibase)?;
+    }
+    if args.infer_width {
+        evaluator.settings_mut().set_infer_width(true);
+    }
+    if let Some(dialect) = args.dialect {
+        evaluator.settings_mut().set_dialect(dialect);
+    }
+    if args.implicit_mul {
+        evaluator.settings_mut().set_implicit_mul(true);
+    }
+    if args.list_functions {
+        let width = evaluator
+            .functions()
+            .map(|function| function.name().len())
+            .max()
+            .unwrap_or(0);
+        for function in evaluator.functions() {
+            println!("{:<width$}  {}", function.name(), function.doc());
+        }
+        return Ok(());
+    }
+    if let Some(name) = &args.help_fn {
+        let function = evaluator
+            .function(name)
+            .with_context(|| format!("No such function: {name}"))?;
+        println!("{}", function.name());
+        println!("    {}", function.doc());
+        if !function.aliases().is_empty() {
+            println!("Aliases: {}", function.aliases().join(", "));
+        }
+        println!("Arguments: {}", function.signature());
+        if !function.examples().is_empty() {
+            println!("Examples:");
+            for example in function.examples() {
+                println!("    {} = {}", example.expr, example.result);
             }
-            for (s, e) in (num_str.len() % 4..num_str.len())
-                .step_by(4)
-                .map(|s| (s, s + 4))
-            {
-                str_sections.push(&num_str[s..e]);
+        }
+        return Ok(());
+    }
+    let result = evaluator.eval(&expr);
+    for warning in evaluator.warnings(&expr).unwrap_or_default() {
+        eprintln!("warning: {warning}");
+    }
+    if args.format.json {
+        match result {
+            Ok(result) => println!("{}", serde_json::to_string(&result)?),
+            Err(error) => {
+                println!("{}", serde_json::json!({ "error": error }));
+                std::process::exit(1);
             }
-
-            println!("0x{}", str_sections.join("_"));
         }
+        return Ok(());
+    }
+    let result = result?;
+    // C expressions are formatted at the width of their type, so `~0` is `FFFFFFFF`
+    let type_width = evaluator.type_width(&expr)?.map(|width| width as u32);
+    let output = if args.format.is_default() {
+        evaluator.settings().output()
+    } else {
+        OutputMode::Decimal
+    };
+    let radix = if let Some(obase) = args.format.obase {
+        Some((obase, "--obase"))
+    } else if args.format.hex {
+        Some((16, "--hex"))
     } else if args.format.bin {
+        Some((2, "--bin"))
+    } else if args.format.oct {
+        Some((8, "--oct"))
+    } else if (args.format.auto || output == OutputMode::Auto)
+        && matches!(result, Operand::Integer(_))
+    {
+        evaluator.input_radix(&expr)?.map(|radix| (radix, "--auto"))
+    } else if let OutputMode::Radix(radix) = output {
+        Some((radix, "output"))
+    } else if args.pad.is_some() {
+        Some((10, "--pad"))
+    } else {
+        None
+    };
+    if let Some((radix, flag)) = radix {
         let num = result.as_int().with_context(|| {
             format!(
-                "--bin flag requires the result of the expression to be integer. Got {result:?}",
+                "{flag} flag requires the result of the expression to be integer. Got {result:?}",
             )
         })?;
-        if args.pretty == 0 {
-            println!("{num:b}");
-        } else if args.pretty == 1 {
-            println!("{num:#b}");
-        } else {
-            let num_str = format!("{num:b}");
-            let mut str_sections = vec![];
-            if num_str.len() % 4 != 0 {
-                str_sections.push(&num_str[0..num_str.len() % 4]);
-            }
-            for (s, e) in (num_str.len() % 4..num_str.len())
-                .step_by(4)
-                .map(|s| (s, s + 4))
-            {
-                str_sections.push(&num_str[s..e]);
-            }
-
-            println!("0b{}", str_sections.join("_"));
-        }
-    } else if args.format.oct {
+        let num = check_pad(num, &args)?;
+        // without --pad, inferred widths pad the output as long as the result fits
+        let pad = match args.pad {
+            Some(pad) => Some(pad),
+            None if evaluator.settings().infer_width() => evaluator
+                .input_width(&expr)?
+                .map(|width| width as u32)
+                .filter(|width| fits_width(num, *width)),
+            None => None,
+        };
+        let sign_width = type_width.unwrap_or(Integer::BITS);
+        println!(
+            "{}",
+            radix_format(radix, pad, sign_width, args.pretty).format(num)
+        );
+    } else if args.format.verilog {
         let num = result.as_int().with_context(|| {
             format!(
-                "--oct flag requires the result of the expression to be integer. Got {result:?}",
+                "--verilog flag requires the result of the expression to be integer. Got {result:?}"
             )
         })?;
-        if args.pretty == 0 {
-            println!("{num:o}");
-        } else if args.pretty == 1 {
-            println!("{num:#o}");
-        } else {
-            let num_str = format!("{num:o}");
-            let mut str_sections = vec![];
-            if num_str.len() % 4 != 0 {
-                str_sections.push(&num_str[0..num_str.len() % 4]);
-            }
-            for (s, e) in (num_str.len() % 4..num_str.len())
-                .step_by(4)
-                .map(|s| (s, s + 4))
-            {
-                str_sections.push(&num_str[s..e]);
-            }
-
-            println!("0o{}", str_sections.join("_"));
+        let width = match args.width {
+            Some(width) => width,
+            None => evaluator
+                .input_width(&expr)?
+                .map(|width| width as u32)
+                .or(type_width)
+                .unwrap_or_else(|| fit_width(num)),
+        };
+        if !fits_width(num, width) {
+            eprintln!("warning: {num} does not fit into {width} bits, truncated");
         }
+        println!("{}", verilog(num, width, args.pretty > 1));
     } else if args.format.bits {
         let result = result.as_int().with_context(|| {
             format!(
@@ -135,6 +267,32 @@ fn main() -> anyhow::Result<()> {
             }
         }
         println!("{}", list.join(", "));
+    } else if args.format.ruler {
+        let num = result.as_int().with_context(|| {
+            format!(
+                "--ruler flag requires the result of the expression to be integer. Got {result:?}"
+            )
+        })?;
+        let width = args.width.or(type_width).unwrap_or_else(|| fit_width(num));
+        print!("{}", bit_ruler(num, width, args.color));
+        let ranges = set_bit_ranges(num, width)
+            .into_iter()
+            .map(|range| {
+                if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}-{}", range.start(), range.end())
+                }
+            })
+            .collect::<Vec<_>>();
+        println!("set bits: {}", ranges.join(", "));
+    } else if args.format.all {
+        let num = result.as_int().with_context(|| {
+            format!(
+                "--all flag requires the result of the expression to be integer. Got {result:?}"
+            )
+        })?;
+        println!("{}", format_all(num, args.width.or(type_width)));
     } else if args.format.exp {
         let result = result.as_float().with_context(|| {
             format!(
@@ -147,26 +305,255 @@ fn main() -> anyhow::Result<()> {
             Operand::Float(f) => println!("{f}"),
             Operand::String(s) => println!("{s}"),
             Operand::Integer(num) => {
-                if args.pretty == 0 {
-                    println!("{num}");
+                let format = if args.pretty == 0 {
+                    NumberFormat::dec()
                 } else {
-                    let num_str = format!("{num}");
-                    let mut str_sections = vec![];
-                    if num_str.len() % 3 != 0 {
-                        str_sections.push(&num_str[0..num_str.len() % 3]);
-                    }
-                    for (s, e) in (num_str.len() % 3..num_str.len())
-                        .step_by(3)
-                        .map(|s| (s, s + 3))
-                    {
-                        str_sections.push(&num_str[s..e]);
-                    }
-
-                    println!("{}", str_sections.join(","));
-                }
+                    NumberFormat::dec().group(3, Separator::Comma)
+                };
+                println!("{}", format.format(num));
             }
         }
     }
 
     Ok(())
 }
+
+impl PrintFormat {
+    /// Whether no format is selected
+    fn is_default(&self) -> bool {
+        !(self.dec
+            || self.auto
+            || self.hex
+            || self.obase.is_some()
+            || self.oct
+            || self.bin
+            || self.exp
+            || self.verilog
+            || self.bits
+            || self.ruler
+            || self.json
+            || self.all)
+    }
+}
+
+fn parse_radix(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(radix @ 2..=36) => Ok(radix),
+        _ => Err("must be in the range of 2 to 36".to_string()),
+    }
+}
+
+fn parse_dialect(s: &str) -> Result<Dialect, String> {
+    match s {
+        "rcal" => Ok(Dialect::Rcal),
+        "c" => Ok(Dialect::C),
+        _ => Err("must be rcal or c".to_string()),
+    }
+}
+
+fn parse_pad(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(bits @ (8 | 16 | 32 | 64 | 128)) => Ok(bits),
+        _ => Err("must be one of 8, 16, 32, 64 and 128".to_string()),
+    }
+}
+
+/// The number to print with --pad, truncated to the unsigned value of its lowest bits
+/// with --truncate when it does not fit
+fn check_pad(num: Integer, args: &Cli) -> anyhow::Result<Integer> {
+    let Some(pad) = args.pad else {
+        return Ok(num);
+    };
+    if fit_width(num) <= pad {
+        return Ok(num);
+    }
+    if !args.truncate {
+        anyhow::bail!("{num} does not fit into {pad} bits, use --truncate to truncate it");
+    }
+    eprintln!("warning: {num} does not fit into {pad} bits, truncated");
+    Ok((num as u128 & (u128::MAX >> (128 - pad))) as Integer)
+}
+
+/// Format of `--hex`, `--oct` and `--bin` according to the pretty level and padding.
+/// Without padding, negative numbers are in two's complement of `sign_width` bits
+fn radix_format(radix: u32, pad: Option<u32>, sign_width: u32, pretty: u8) -> NumberFormat {
+    let format = NumberFormat::new(radix).uppercase(true);
+    // negative numbers are signed in radixes other than powers of 2, like decimal
+    let format = match pad {
+        Some(bits) if radix.is_power_of_two() => format
+            .sign(Sign::TwosComplement(bits))
+            .min_width(bits.div_ceil(radix.ilog2()) as usize),
+        Some(bits) => {
+            let max = (u128::MAX >> (128 - bits)) as Integer;
+            format.min_width(NumberFormat::new(radix).format(max).len())
+        }
+        None if radix.is_power_of_two() => format.sign(Sign::TwosComplement(sign_width)),
+        None => format,
+    };
+    match pretty {
+        0 => format,
+        1 => format.prefix(true),
+        _ => format.prefix(true).group(4, Separator::Underscore),
+    }
+}
+
+/// Summary of `--all`, in two's complement of `width` bits or the smallest register that holds the value
+fn format_all(num: Integer, width: Option<u32>) -> String {
+    let width = width.unwrap_or_else(|| fit_width(num));
+    let mask = u128::MAX >> (128 - width);
+    let unsigned = num as u128 & mask;
+    let signed = if width < 128 && unsigned >> (width - 1) != 0 {
+        unsigned as Integer - (1 << width)
+    } else {
+        unsigned as Integer
+    };
+    let w = width as usize;
+
+    let mut lines = vec![
+        format!("{:<10}: {signed}", format!("DEC (i{width})")),
+        format!("{:<10}: {unsigned}", format!("DEC (u{width})")),
+    ];
+    let format = |radix: u32, digit_bits: usize| {
+        NumberFormat::new(radix)
+            .prefix(true)
+            .uppercase(true)
+            .sign(Sign::TwosComplement(width))
+            .min_width(w.div_ceil(digit_bits))
+    };
+    lines.push(format!(
+        "{:<10}: {}",
+        "HEX",
+        format(16, 4).group(4, Separator::Underscore).format(signed)
+    ));
+    lines.push(format!("{:<10}: {}", "OCT", format(8, 3).format(signed)));
+    lines.push(format!(
+        "{:<10}: {}",
+        "BIN",
+        format(2, 1).group(4, Separator::Underscore).format(signed)
+    ));
+    let bits = (0..width)
+        .filter(|i| unsigned & (1 << i) != 0)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>();
+    lines.push(format!("{:<10}: {}", "BITS", bits.join(", ")));
+
+    let bytes = unsigned.to_be_bytes();
+    let bytes = &bytes[bytes.iter().take_while(|b| **b == 0).count()..];
+    if !bytes.is_empty() && bytes.iter().all(|b| (0x20..0x7F).contains(b)) {
+        let ascii = bytes.iter().map(|b| *b as char).collect::<String>();
+        lines.push(format!("{:<10}: {ascii:?}", "ASCII"));
+    }
+
+    // only values aligned to half a KiB are considered sizes
+    if unsigned >= 1024 && unsigned.is_multiple_of(512) {
+        let (unit, name) = [
+            (1u128 << 50, "PiB"),
+            (1 << 40, "TiB"),
+            (1 << 30, "GiB"),
+            (1 << 20, "MiB"),
+            (1 << 10, "KiB"),
+        ]
+        .into_iter()
+        .find(|(unit, _)| unsigned >= *unit)
+        .unwrap();
+        lines.push(format!(
+            "{:<10}: {} {name}",
+            "SIZE",
+            unsigned as f64 / unit as f64
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_all() {
+        assert_eq!(
+            format_all(0x4142, None),
+            [
+                "DEC (i16) : 16706",
+                "DEC (u16) : 16706",
+                "HEX       : 0x4142",
+                "OCT       : 0o040502",
+                "BIN       : 0b0100_0001_0100_0010",
+                "BITS      : 1, 6, 8, 14",
+                "ASCII     : \"AB\"",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            format_all(-1, Some(12)),
+            [
+                "DEC (i12) : -1",
+                "DEC (u12) : 4095",
+                "HEX       : 0xFFF",
+                "OCT       : 0o7777",
+                "BIN       : 0b1111_1111_1111",
+                "BITS      : 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11",
+            ]
+            .join("\n")
+        );
+        // bits above --width are discarded
+        let all = format_all(0x1_0000_0800, Some(12));
+        assert!(all.starts_with("DEC (i12) : -2048\nDEC (u12) : 2048\nHEX       : 0x800\n"));
+        assert!(format_all(3 << 20, None).ends_with("SIZE      : 3 MiB"));
+        assert!(format_all(-1, None).starts_with("DEC (i8)  : -1\nDEC (u8)  : 255\n"));
+    }
+
+    #[test]
+    fn test_width_args() {
+        let cli = Cli::try_parse_from(["rcal", "-a", "-w", "12", "1 + 2"]).unwrap();
+        assert!(cli.format.all);
+        assert_eq!(cli.width, Some(12));
+        for width in ["0", "129", "x"] {
+            assert!(Cli::try_parse_from(["rcal", "-a", "-w", width, "1"]).is_err());
+        }
+        // --all is one of the output formats
+        assert!(Cli::try_parse_from(["rcal", "-a", "-x", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(radix_format(16, Some(16), 128, 0).format(0xF0), "00F0");
+        assert_eq!(
+            radix_format(16, Some(32), 128, 2).format(0xF0),
+            "0x0000_00F0"
+        );
+        assert_eq!(radix_format(16, Some(16), 128, 1).format(-2), "0xFFFE");
+        assert_eq!(radix_format(2, Some(8), 128, 2).format(5), "0b0000_0101");
+        assert_eq!(radix_format(8, Some(16), 128, 1).format(8), "0o000010");
+        assert_eq!(radix_format(10, Some(8), 128, 0).format(5), "005");
+        assert_eq!(radix_format(10, Some(8), 128, 0).format(-5), "-005");
+        assert_eq!(radix_format(16, None, 32, 0).format(-1), "FFFFFFFF");
+
+        let args = |flags: &[&str]| Cli::try_parse_from([&["rcal"], flags, &["1"]].concat());
+        let pad8 = args(&["--pad", "8", "-x"]).unwrap();
+        assert_eq!(check_pad(255, &pad8).unwrap(), 255);
+        assert_eq!(check_pad(-128, &pad8).unwrap(), -128);
+        assert!(check_pad(256, &pad8).is_err());
+        let truncate = args(&["--pad", "8", "--truncate", "-x"]).unwrap();
+        assert_eq!(check_pad(300, &truncate).unwrap(), 44);
+        assert_eq!(check_pad(-300, &truncate).unwrap(), 0xD4);
+        // every radix prints the truncated value, not only the two's complement ones
+        for (radix, expected) in [(10, "044"), (16, "2C"), (3, "001122")] {
+            let num = check_pad(300, &truncate).unwrap();
+            assert_eq!(radix_format(radix, Some(8), 128, 0).format(num), expected);
+        }
+
+        for pad in ["12", "0", "256"] {
+            assert!(args(&["--pad", pad, "-x"]).is_err(), "{pad}");
+        }
+        assert!(args(&["--truncate", "-x"]).is_err());
+        // --pad applies to the radix output, and is rejected by the other formats
+        for flag in ["-d", "--auto", "-x", "-o", "-b"] {
+            assert!(args(&["--pad", "16", flag]).is_ok(), "{flag}");
+        }
+        assert!(args(&["--pad", "16", "--obase", "3"]).is_ok());
+        for flag in ["-a", "--verilog", "--bits", "-r", "--json", "-e"] {
+            assert!(args(&["--pad", "16", flag]).is_err(), "{flag}");
+        }
+    }
+}